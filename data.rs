@@ -1,16 +1,593 @@
-use ink::prelude::string::String;
+use ink::prelude::{string::String, vec::Vec};
+use ink::{H160, H256, U256};
 
 /// PSP22 error types
-#[derive(Debug, PartialEq, Eq)]
+///
+/// Every variant is pinned to its SCALE index, which is also its `code()`: the
+/// first four match the original PSP22 errors, and new variants take the next
+/// free index, so existing codes never change.
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
 pub enum PSP22Error {
     /// Returned if the account doesn't have enough balance to complete the operation.
+    #[codec(index = 0)]
     InsufficientBalance,
     /// Returned if the caller doesn't have enough allowance to complete the operation.
+    #[codec(index = 1)]
     InsufficientAllowance,
     /// Returned if the operation would cause an overflow.
+    #[codec(index = 2)]
     Overflow,
-    /// Custom error with a message
+    /// Custom error with a message.
+    ///
+    /// Deprecated: the contract never returns it. It keeps index 3 so clients
+    /// built against the original PSP22 errors still decode, and `to_legacy`
+    /// produces it off-chain.
+    #[codec(index = 3)]
     Custom(String),
+    /// Returned if the allowance being used has expired.
+    #[codec(index = 4)]
+    AllowanceExpired,
+    /// Returned if the caller isn't allowed to perform the operation.
+    #[codec(index = 5)]
+    Unauthorized,
+    /// Returned if the operation requires a non-zero amount.
+    #[codec(index = 6)]
+    ZeroAmount,
+    /// Returned if the operation needs the token to wrap the native currency.
+    #[codec(index = 7)]
+    NotWrapped,
+    /// Returned if the operation is unavailable on a wrapped token.
+    #[codec(index = 8)]
+    WrappedMode,
+    /// Returned if a signature can't be recovered or wasn't made by an authorized signer.
+    #[codec(index = 9)]
+    InvalidSignature,
+    /// Returned if a mint voucher is used past its expiry.
+    #[codec(index = 10)]
+    VoucherExpired,
+    /// Returned if a mint voucher nonce was already used.
+    #[codec(index = 11)]
+    VoucherAlreadyRedeemed,
+    /// Returned if storage is already at the current version.
+    #[codec(index = 12)]
+    AlreadyMigrated,
+    /// Returned if the contract code couldn't be replaced.
+    #[codec(index = 13)]
+    UpgradeFailed,
+    /// Returned if the target contract of a call-and-notify didn't accept it.
+    #[codec(index = 14)]
+    ReceiverRejected,
+    /// Returned if the recipient can't receive the operation.
+    #[codec(index = 15)]
+    InvalidRecipient,
+    /// Returned if no escrow exists under the given id.
+    #[codec(index = 16)]
+    EscrowNotFound,
+    /// Returned if the escrow was already released or refunded.
+    #[codec(index = 17)]
+    EscrowClosed,
+    /// Returned if the escrow can't be refunded before its deadline.
+    #[codec(index = 18)]
+    EscrowNotExpired,
+    /// Returned if the deadline isn't in the future.
+    #[codec(index = 19)]
+    InvalidDeadline,
+    /// Returned if no stream exists under the given id.
+    #[codec(index = 20)]
+    StreamNotFound,
+    /// Returned if a stream's start and stop times are invalid.
+    #[codec(index = 21)]
+    InvalidSchedule,
+    /// Returned if rewards are deposited while nothing is staked.
+    #[codec(index = 22)]
+    NothingStaked,
+    /// Returned if unstaked tokens are still locked.
+    #[codec(index = 23)]
+    StillUnbonding,
+    /// Returned if dividends are distributed while there are no holders.
+    #[codec(index = 24)]
+    NoHolders,
+    /// Returned if sending native currency failed.
+    #[codec(index = 25)]
+    NativeTransferFailed,
+    /// Returned if the foreign token transfer failed.
+    #[codec(index = 26)]
+    RecoveryFailed,
+    /// Returned if no ownership transfer is pending.
+    #[codec(index = 27)]
+    NoPendingOwner,
+    /// Returned if the account is already a multisig signer.
+    #[codec(index = 28)]
+    AlreadySigner,
+    /// Returned if the account isn't a multisig signer.
+    #[codec(index = 29)]
+    NotSigner,
+    /// Returned if the multisig threshold would be zero or exceed the signer count.
+    #[codec(index = 30)]
+    InvalidThreshold,
+    /// Returned if the multisig is already in control.
+    #[codec(index = 31)]
+    MultisigEnabled,
+    /// Returned if no proposal exists under the given id.
+    #[codec(index = 32)]
+    ProposalNotFound,
+    /// Returned if the signer already confirmed the proposal.
+    #[codec(index = 33)]
+    AlreadyConfirmed,
+    /// Returned if the signer hasn't confirmed the proposal.
+    #[codec(index = 34)]
+    NotConfirmed,
+    /// Returned if too few signers confirmed the proposal.
+    #[codec(index = 35)]
+    NotEnoughConfirmations,
+    /// Returned if the action has to be scheduled through the timelock.
+    #[codec(index = 36)]
+    Timelocked,
+    /// Returned if the timelock is already enabled.
+    #[codec(index = 37)]
+    TimelockEnabled,
+    /// Returned if scheduling while the timelock is disabled.
+    #[codec(index = 38)]
+    TimelockDisabled,
+    /// Returned if the eta is closer than the timelock minimum delay.
+    #[codec(index = 39)]
+    DelayTooShort,
+    /// Returned if no scheduled operation exists under the given id.
+    #[codec(index = 40)]
+    OperationNotFound,
+    /// Returned if the eta of the operation hasn't passed yet.
+    #[codec(index = 41)]
+    OperationNotReady,
+    /// Returned if governance hasn't been enabled.
+    #[codec(index = 42)]
+    GovernanceDisabled,
+    /// Returned if the voting period is zero.
+    #[codec(index = 43)]
+    InvalidVotingPeriod,
+    /// Returned if a proposal has no actions.
+    #[codec(index = 44)]
+    EmptyProposal,
+    /// Returned if the account held no tokens at the snapshot.
+    #[codec(index = 45)]
+    NoVotingPower,
+    /// Returned if the account already voted on the proposal.
+    #[codec(index = 46)]
+    AlreadyVoted,
+    /// Returned if the proposal isn't accepting votes.
+    #[codec(index = 47)]
+    VotingClosed,
+    /// Returned if the proposal can't be queued.
+    #[codec(index = 48)]
+    ProposalNotSucceeded,
+    /// Returned if the proposal can't be executed.
+    #[codec(index = 49)]
+    ProposalNotQueued,
+    /// Returned if tokens or allowances would be assigned to the zero address.
+    #[codec(index = 50)]
+    ZeroAddress,
+    /// Returned if an authorization is used before its validity window opens.
+    #[codec(index = 51)]
+    AuthorizationNotYetValid,
+    /// Returned if an authorization is used after its validity window closed.
+    #[codec(index = 52)]
+    AuthorizationExpired,
+    /// Returned if the authorization nonce was already used or canceled.
+    #[codec(index = 53)]
+    AuthorizationAlreadyUsed,
+    /// Returned if a holder tries to authorize or revoke itself as operator.
+    #[codec(index = 54)]
+    InvalidOperator,
+    /// Returned if the bridge has no rate limit set for the chain.
+    #[codec(index = 55)]
+    UnsupportedChain,
+    /// Returned if the inbound transfer was already minted.
+    #[codec(index = 56)]
+    BridgeMessageProcessed,
+    /// Returned if too few distinct relayers signed the inbound transfer.
+    #[codec(index = 57)]
+    NotEnoughAttestations,
+    /// Returned if the inbound transfer exceeds the chain's rate limit.
+    #[codec(index = 58)]
+    RateLimitExceeded,
+    /// Returned if the account is already a bridge relayer.
+    #[codec(index = 59)]
+    AlreadyRelayer,
+    /// Returned if the account isn't a bridge relayer.
+    #[codec(index = 60)]
+    NotRelayer,
+    /// Returned if a signed message reaches a token deployed without a chain id.
+    #[codec(index = 61)]
+    ChainIdNotSet,
+    /// Returned if an argument is outside the range the operation accepts.
+    #[codec(index = 62)]
+    InvalidParameter,
+    /// Returned if the code was upgraded and storage awaits `migrate`.
+    #[codec(index = 63)]
+    MigrationRequired,
+    /// Returned if the proposer's voting power is below the proposal threshold.
+    #[codec(index = 64)]
+    BelowProposalThreshold,
 }
 
+impl PSP22Error {
+    /// Stable numeric code of the error, equal to its SCALE variant index
+    pub fn code(&self) -> u8 {
+        ink::scale::Encode::using_encoded(self, |encoded| encoded[0])
+    }
+
+    /// Converts the error for clients that only decode the original PSP22 variants
+    ///
+    /// Other variants become `Custom` carrying the variant name. Only built
+    /// with `std`, for off-chain clients: the contract itself never formats
+    /// error names.
+    #[cfg(feature = "std")]
+    pub fn to_legacy(&self) -> PSP22Error {
+        match self {
+            PSP22Error::InsufficientBalance => PSP22Error::InsufficientBalance,
+            PSP22Error::InsufficientAllowance => PSP22Error::InsufficientAllowance,
+            PSP22Error::Overflow => PSP22Error::Overflow,
+            PSP22Error::Custom(message) => PSP22Error::Custom(message.clone()),
+            other => PSP22Error::Custom(ink::prelude::format!("{other:?}")),
+        }
+    }
+}
+
+/// Token metadata, kept in its own storage cell so only the metadata
+/// messages pay for decoding the strings
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct Metadata {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+/// Everything the contract tracks for a single account.
+///
+/// Packed into one `Mapping` value so the hot paths (`balance_of`, transfers)
+/// read an account with a single storage access.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct AccountData {
+    /// Spendable token balance.
+    pub balance: U256,
+    /// Replay counter for account-signed operations.
+    ///
+    /// Reserved: nothing reads or increments it yet.
+    pub nonce: u64,
+    /// Whether the account is frozen.
+    ///
+    /// Reserved: transfers don't check it yet.
+    pub frozen: bool,
+    /// Part of `balance` that is locked.
+    ///
+    /// Reserved: transfers don't enforce it yet.
+    pub locked: U256,
+    /// Dividend-per-share value the dividends were last settled at.
+    pub dividend_per_share_paid: U256,
+    /// Settled dividends not withdrawn yet.
+    pub dividend_credit: U256,
+}
+
+impl AccountData {
+    /// Returns whether the record holds no state worth keeping in storage.
+    ///
+    /// The dividend checkpoint doesn't count: without a balance nothing is
+    /// earned, whatever value it was settled at.
+    pub fn is_empty(&self) -> bool {
+        self.balance.is_zero()
+            && self.nonce == 0
+            && !self.frozen
+            && self.locked.is_zero()
+            && self.dividend_credit.is_zero()
+    }
+}
+
+/// Amount a spender may still transfer on behalf of an owner
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct Allowance {
+    /// Remaining amount.
+    pub value: U256,
+    /// Timestamp (ms) from which the allowance can no longer be used.
+    pub expires_at: Option<u64>,
+}
+
+impl Allowance {
+    /// Returns whether the allowance can no longer be used at `now`.
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+}
+
+/// Lifecycle of an escrow
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub enum EscrowStatus {
+    /// Tokens are held by the contract.
+    Open,
+    /// Tokens were paid out to the payee.
+    Released,
+    /// Tokens were returned to the payer.
+    Refunded,
+}
+
+/// Tokens held by the contract until they are released to the payee or refunded
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct Escrow {
+    /// Account that locked the tokens and gets them back on refund.
+    pub payer: H160,
+    /// Account the tokens are released to.
+    pub payee: H160,
+    /// Account that may release or refund at any time.
+    pub arbiter: H160,
+    /// Amount of tokens held.
+    pub amount: U256,
+    /// Timestamp (ms) from which anyone may refund the payer.
+    pub deadline: u64,
+    /// Current state.
+    pub status: EscrowStatus,
+}
+
+/// Tokens paid out linearly from `sender` to `recipient` between `start` and `stop`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct Stream {
+    /// Account that funded the stream.
+    pub sender: H160,
+    /// Account the tokens are streamed to.
+    pub recipient: H160,
+    /// Total amount streamed over the whole schedule.
+    pub deposit: U256,
+    /// Timestamp (ms) at which streaming starts.
+    pub start: u64,
+    /// Timestamp (ms) at which the whole deposit has been streamed.
+    pub stop: u64,
+    /// Amount the recipient has already withdrawn.
+    pub withdrawn: U256,
+}
+
+impl Stream {
+    /// Returns the amount streamed to the recipient by `now`, rounded down.
+    pub fn streamed_at(&self, now: u64) -> U256 {
+        if now <= self.start {
+            return U256::from(0);
+        }
+        if now >= self.stop {
+            return self.deposit;
+        }
+
+        let elapsed = U256::from(now - self.start);
+        let duration = U256::from(self.stop - self.start);
+
+        // Split the deposit so `deposit * elapsed` can't overflow
+        let per_unit = self.deposit / duration;
+        let remainder = self.deposit % duration;
+        per_unit * elapsed + remainder * elapsed / duration
+    }
+}
+
+/// Staking position of a single account
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct StakeInfo {
+    /// Tokens currently staked.
+    pub staked: U256,
+    /// Reward-per-token accumulator value the rewards were last settled at.
+    pub reward_per_token_paid: U256,
+    /// Settled rewards not claimed yet.
+    pub rewards: U256,
+    /// Unstaked tokens waiting for the unbonding period to pass.
+    pub unbonding: U256,
+    /// Timestamp (ms) from which the unbonding tokens can be withdrawn.
+    pub unbonding_release: u64,
+}
+
+impl StakeInfo {
+    /// Returns whether the record holds no state worth keeping in storage.
+    pub fn is_empty(&self) -> bool {
+        self.staked.is_zero() && self.rewards.is_zero() && self.unbonding.is_zero()
+    }
+}
+
+/// Privileged operation, applied directly by the owner or through the multisig
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub enum AdminAction {
+    /// Mint `value` new tokens to `to`.
+    Mint { to: H160, value: U256 },
+    /// Accept mint vouchers signed by `signer`.
+    AddVoucherSigner { signer: H160 },
+    /// Stop accepting mint vouchers signed by `signer`.
+    RemoveVoucherSigner { signer: H160 },
+    /// Set the staking reward emissions per millisecond.
+    SetRewardRate { reward_rate: U256 },
+    /// Set how long unstaked tokens stay locked, in milliseconds.
+    SetUnbondingPeriod { unbonding_period: u64 },
+    /// Replace the contract code.
+    Upgrade { code_hash: H256 },
+    /// Migrate storage to the layout of the current code.
+    Migrate,
+    /// Send PSP22 tokens held by mistake to `to`.
+    RecoverPsp22 { token: H160, to: H160, amount: U256 },
+    /// Send native currency held by mistake to `to`.
+    RecoverNative { to: H160, amount: U256 },
+    /// Nominate `new_owner` as the next owner.
+    TransferOwnership { new_owner: H160 },
+    /// Withdraw the pending owner nomination.
+    CancelOwnershipTransfer,
+    /// Hand control to an M-of-N multisig.
+    EnableMultisig { signers: Vec<H160>, threshold: u32 },
+    /// Add a multisig signer.
+    AddSigner { signer: H160 },
+    /// Remove a multisig signer.
+    RemoveSigner { signer: H160 },
+    /// Set how many signers must confirm a proposal.
+    SetThreshold { threshold: u32 },
+    /// Subject privileged actions to a minimum delay, in milliseconds.
+    EnableTimelock { min_delay: u64 },
+    /// Set the timelock minimum delay, in milliseconds.
+    SetTimelockDelay { min_delay: u64 },
+    /// Drop a scheduled operation.
+    CancelScheduled { id: u64 },
+    /// Set the votes in favor a proposal needs and the voting period in blocks.
+    SetGovernanceSettings { quorum: U256, voting_period: u32 },
+    /// Add a bridge relayer.
+    AddRelayer { relayer: H160 },
+    /// Remove a bridge relayer.
+    RemoveRelayer { relayer: H160 },
+    /// Set how many relayers must attest an inbound transfer.
+    SetRelayerThreshold { threshold: u32 },
+    /// Cap inbound minting from `chain`; a zero `limit` removes the chain.
+    SetBridgeRateLimit { chain: u64, limit: U256, period: u64 },
+    /// Set the voting power needed to create a governance proposal.
+    SetProposalThreshold { threshold: U256 },
+}
+
+impl AdminAction {
+    /// Whether the action has to wait out the timelock delay when it is enabled
+    ///
+    /// Canceling is exempt so a bad operation can always be stopped in time,
+    /// and migrating because the upgrade it completes already waited.
+    pub fn is_timelocked(&self) -> bool {
+        !matches!(
+            self,
+            AdminAction::CancelScheduled { .. } | AdminAction::Migrate
+        )
+    }
+
+    /// Whether the action can run while storage still awaits `migrate`
+    ///
+    /// Migrating, upgrading again and canceling scheduled operations are all
+    /// that's needed to finish or back out of an upgrade.
+    pub fn runs_before_migration(&self) -> bool {
+        matches!(
+            self,
+            AdminAction::Migrate
+                | AdminAction::Upgrade { .. }
+                | AdminAction::CancelScheduled { .. }
+        )
+    }
+}
+
+/// Administrative action waiting for multisig confirmations
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct MultisigProposal {
+    /// Action performed once enough signers confirmed.
+    pub action: AdminAction,
+    /// Signers that confirmed the proposal.
+    pub confirmations: Vec<H160>,
+}
+
+/// Administrative action scheduled by the timelock
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct TimelockOperation {
+    /// Action performed once the operation is ready.
+    pub action: AdminAction,
+    /// Timestamp from which the operation can be executed.
+    pub eta: u64,
+}
+
+/// Balance of an account from `block` on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct Checkpoint {
+    /// Block the balance was last changed in.
+    pub block: u32,
+    /// Balance at the end of that block.
+    pub balance: U256,
+}
+
+/// Lifecycle stage of a governance proposal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub enum ProposalState {
+    /// Votes are being cast.
+    Active,
+    /// Voting ended without a majority in favor or without quorum.
+    Defeated,
+    /// Voting ended in favor; the proposal can be queued.
+    Succeeded,
+    /// Waiting for its eta to be executed.
+    Queued,
+    /// Actions were performed.
+    Executed,
+}
+
+/// Set of administrative actions put to a token-weighted vote
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct GovernanceProposal {
+    /// Account that created the proposal.
+    pub proposer: H160,
+    /// Hash of the off-chain description.
+    pub description_hash: H256,
+    /// Actions performed in order when the proposal is executed.
+    pub actions: Vec<AdminAction>,
+    /// Block whose closing balances determine voting power.
+    pub snapshot_block: u32,
+    /// Last block votes are accepted in.
+    pub vote_end: u32,
+    /// Votes in favor needed, fixed at creation.
+    pub quorum: U256,
+    /// Voting power in favor.
+    pub for_votes: U256,
+    /// Voting power against.
+    pub against_votes: U256,
+    /// Time the proposal was queued.
+    pub queued_at: Option<u64>,
+    /// Earliest execution time, set when queued.
+    pub eta: Option<u64>,
+    /// Whether the actions were performed.
+    pub executed: bool,
+}
+
+/// Cap on tokens minted from one source chain per time window
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct BridgeRateLimit {
+    /// Most tokens minted per window.
+    pub limit: U256,
+    /// Length of a window in milliseconds; never zero.
+    pub period: u64,
+    /// Timestamp the current window started at.
+    pub window_start: u64,
+    /// Tokens minted in the current window.
+    pub used: U256,
+}
+
+impl BridgeRateLimit {
+    /// Accounts for minting `amount` at `now`, starting a new window if the last one has passed
+    pub fn consume(&mut self, now: u64, amount: U256) -> Result<(), PSP22Error> {
+        if now >= self.window_start.saturating_add(self.period) {
+            self.window_start = now;
+            self.used = U256::from(0);
+        }
+
+        let used = self.used.checked_add(amount).ok_or(PSP22Error::Overflow)?;
+        if used > self.limit {
+            return Err(PSP22Error::RateLimitExceeded);
+        }
+
+        self.used = used;
+        Ok(())
+    }
+}
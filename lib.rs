@@ -3,13 +3,92 @@
 mod data;
 mod traits;
 
+pub use self::psp_coin::{PspCoin, PspCoinRef};
+pub use data::PSP22Error;
+
 #[ink::contract]
 mod psp_coin {
     use ink::prelude::string::String;
     use ink::prelude::vec::Vec;
-    use ink::{H160, U256, storage::Mapping};
-
-    use crate::data::PSP22Error;
+    use ink::env::CallFlags;
+    use ink::env::call::{ExecutionInput, Selector, build_call};
+    use ink::env::hash::Keccak256;
+    use ink::storage::{Lazy, Mapping};
+    use ink::{H160, H256, U256};
+
+    use crate::data::{
+        AccountData, AdminAction, Allowance, BridgeRateLimit, Checkpoint, Escrow, EscrowStatus,
+        GovernanceProposal, Metadata, MultisigProposal, PSP22Error, ProposalState, StakeInfo,
+        Stream, TimelockOperation,
+    };
+
+    /// Storage layout version written by this code.
+    ///
+    /// Version 1 is the first upgradeable layout. Storage written by earlier
+    /// builds (a `balances` mapping and metadata in the root cell) can't be
+    /// migrated to it; those contracts have to be redeployed.
+    ///
+    /// Bump it whenever a release needs `migrate` to transform existing storage
+    /// and add the matching step to `migrate`. Steps can only rewrite `Lazy` and
+    /// `Mapping` contents, as the packed root fields of `PspCoin` are frozen.
+    /// While storage is behind, state-changing messages fail with
+    /// `MigrationRequired`.
+    pub const STORAGE_VERSION: u32 = 1;
+
+    /// Fixed-point scale of the staking reward-per-token accumulator
+    pub const REWARD_PRECISION: u128 = 1_000_000_000_000_000_000;
+
+    /// Selector of the standard `PSP22::transfer` message used to move foreign tokens
+    pub const PSP22_TRANSFER_SELECTOR: [u8; 4] = ink::selector_bytes!("PSP22::transfer");
+
+    /// Dividends per share are scaled by `2^DIVIDEND_MAGNITUDE_BITS`
+    pub const DIVIDEND_MAGNITUDE_BITS: usize = 128;
+
+    /// Type of mint vouchers, whose hash leads every voucher preimage
+    ///
+    /// Keeps voucher signatures apart from the other messages signed over a
+    /// SCALE-encoded tuple, such as bridge attestations.
+    pub const MINT_VOUCHER_TYPE: &str =
+        "MintVoucher(address to,uint256 amount,uint128 nonce,uint64 expiry)";
+
+    /// EIP-712 domain type; the chain id is the one configured at construction
+    pub const EIP712_DOMAIN_TYPE: &str =
+        "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+
+    /// EIP-3009 type of `transfer_with_authorization` messages
+    pub const TRANSFER_WITH_AUTHORIZATION_TYPE: &str =
+        "TransferWithAuthorization(address from,address to,uint256 value,uint256 validAfter,uint256 validBefore,bytes32 nonce)";
+
+    /// EIP-3009 type of `receive_with_authorization` messages
+    pub const RECEIVE_WITH_AUTHORIZATION_TYPE: &str =
+        "ReceiveWithAuthorization(address from,address to,uint256 value,uint256 validAfter,uint256 validBefore,bytes32 nonce)";
+
+    /// EIP-3009 type of `cancel_authorization` messages
+    pub const CANCEL_AUTHORIZATION_TYPE: &str =
+        "CancelAuthorization(address authorizer,bytes32 nonce)";
+
+    /// Virtual shares backing the vault's share price
+    ///
+    /// With one virtual asset and this many virtual shares, a first depositor
+    /// who donates assets to inflate the share price loses most of the
+    /// donation to the virtual shares, which makes the attack unprofitable.
+    pub const VAULT_VIRTUAL_SHARES: u128 = 1_000;
+
+    /// Most entries returned by a single `holders` query
+    pub const MAX_HOLDERS_PAGE: u32 = 100;
+
+    /// Most entries returned by a single `pending_operations` query
+    pub const MAX_OPERATIONS_PAGE: u32 = 100;
+
+    /// Selector of the `on_transfer_received` message `transfer_and_call` invokes.
+    ///
+    /// Receivers must return this value to accept the tokens.
+    pub const ON_TRANSFER_RECEIVED: [u8; 4] = ink::selector_bytes!("on_transfer_received");
+
+    /// Selector of the `on_approval_received` message `approve_and_call` invokes.
+    ///
+    /// Spenders must return this value to accept the allowance.
+    pub const ON_APPROVAL_RECEIVED: [u8; 4] = ink::selector_bytes!("on_approval_received");
 
     /// Event emitted when tokens are transferred
     #[ink(event)]
@@ -31,644 +110,7548 @@ mod psp_coin {
         value: U256,
     }
 
-    /// Defines the storage of your contract.
-    /// Add new fields to the below struct in order
-    /// to add new static storage fields to your contract.
-    #[ink(storage)]
-    pub struct PspCoin {
-        total_supply: U256,
-        balances: Mapping<H160, U256>,
-        // can owner authorize (allowance > balance)?
-        allowances: Mapping<(H160, H160), U256>, // (owner, spender) -> allowance
-        metadata: (String, String, u8),
+    /// Event emitted when a signed mint voucher is redeemed
+    #[ink(event)]
+    pub struct VoucherRedeemed {
+        #[ink(topic)]
+        to: H160,
+        #[ink(topic)]
+        signer: H160,
+        amount: U256,
+        nonce: u128,
     }
 
-    impl PspCoin {
-        /// Constructor that initializes a memecoin with zero supply
-        #[ink(constructor)]
-        pub fn new() -> Self {
-            Self {
-                total_supply: U256::from(0),
-                balances: Mapping::default(),
-                allowances: Mapping::default(),
-                metadata: (String::from("MemeCoin"), String::from("MEME"), 18),
-            }
-        }
-
-        /// Constructor that initializes a memecoin with initial supply
-        #[ink(constructor)]
-        pub fn new_with_supply(total_supply: U256) -> Self {
-            let caller_h160 = Self::env().caller();
+    /// Event emitted when an account gains or loses the right to sign mint vouchers
+    #[ink(event)]
+    pub struct VoucherSignerChanged {
+        #[ink(topic)]
+        signer: H160,
+        active: bool,
+    }
 
-            let mut balances = Mapping::default();
-            balances.insert(caller_h160, &total_supply);
+    /// Event emitted when the contract code is replaced
+    #[ink(event)]
+    pub struct Upgraded {
+        #[ink(topic)]
+        code_hash: H256,
+    }
 
-            Self {
-                total_supply,
-                balances,
-                allowances: Mapping::default(),
-                metadata: (String::from("MemeCoin"), String::from("MEME"), 18),
-            }
-        }
+    /// Event emitted when storage has been migrated to a new layout version
+    #[ink(event)]
+    pub struct Migrated {
+        from_version: u32,
+        to_version: u32,
+    }
 
-        /// Helper function to get the caller as H160
-        fn caller(&self) -> H160 {
-            self.env().caller()
-        }
+    /// Event emitted when native currency is wrapped into tokens
+    #[ink(event)]
+    pub struct Deposit {
+        #[ink(topic)]
+        dst: H160,
+        value: U256,
+    }
 
-        /// Internal transfer function
-        fn transfer_from_to(
-            &mut self,
-            from: H160,
-            to: H160,
-            value: U256,
-        ) -> Result<(), PSP22Error> {
-            // No-op if from and to are the same or value is zero
-            if from == to || value.is_zero() {
-                return Ok(());
-            }
+    /// Event emitted when tokens are unwrapped back into native currency
+    #[ink(event)]
+    pub struct Withdrawal {
+        #[ink(topic)]
+        src: H160,
+        value: U256,
+    }
 
-            let from_balance = self.balances.get(from).unwrap_or(U256::from(0));
+    /// Event emitted when tokens are locked in a new escrow
+    #[ink(event)]
+    pub struct EscrowOpened {
+        #[ink(topic)]
+        id: u64,
+        #[ink(topic)]
+        payer: H160,
+        #[ink(topic)]
+        payee: H160,
+        arbiter: H160,
+        amount: U256,
+        deadline: u64,
+    }
 
-            if from_balance < value {
-                return Err(PSP22Error::InsufficientBalance);
-            }
+    /// Event emitted when escrowed tokens are paid out to the payee
+    #[ink(event)]
+    pub struct EscrowReleased {
+        #[ink(topic)]
+        id: u64,
+        #[ink(topic)]
+        payee: H160,
+        amount: U256,
+    }
 
-            let to_balance = self.balances.get(to).unwrap_or(U256::from(0));
+    /// Event emitted when escrowed tokens are returned to the payer
+    #[ink(event)]
+    pub struct EscrowRefunded {
+        #[ink(topic)]
+        id: u64,
+        #[ink(topic)]
+        payer: H160,
+        amount: U256,
+    }
 
-            // Check for overflow
-            if to_balance.checked_add(value).is_none() {
-                return Err(PSP22Error::Overflow);
-            }
+    /// Event emitted when a payment stream is created
+    #[ink(event)]
+    pub struct StreamCreated {
+        #[ink(topic)]
+        id: u64,
+        #[ink(topic)]
+        sender: H160,
+        #[ink(topic)]
+        recipient: H160,
+        deposit: U256,
+        start: u64,
+        stop: u64,
+    }
 
-            self.balances.insert(from, &(from_balance - value));
-            self.balances.insert(to, &(to_balance + value));
+    /// Event emitted when the recipient withdraws streamed tokens
+    #[ink(event)]
+    pub struct WithdrawFromStream {
+        #[ink(topic)]
+        id: u64,
+        #[ink(topic)]
+        recipient: H160,
+        amount: U256,
+    }
 
-            self.env().emit_event(Transfer {
-                from: Some(from),
-                to: Some(to),
-                value,
-            });
+    /// Event emitted when a stream is canceled and its remaining funds split
+    #[ink(event)]
+    pub struct StreamCanceled {
+        #[ink(topic)]
+        id: u64,
+        sender_amount: U256,
+        recipient_amount: U256,
+    }
 
-            Ok(())
-        }
+    /// Event emitted when tokens are staked
+    #[ink(event)]
+    pub struct Staked {
+        #[ink(topic)]
+        staker: H160,
+        amount: U256,
     }
 
-    impl PspCoin {
-        // PSP22 Standard Functions
+    /// Event emitted when tokens are unstaked
+    ///
+    /// `release_at` is when unbonded tokens can be withdrawn; tokens are paid
+    /// out right away when there is no unbonding period.
+    #[ink(event)]
+    pub struct Unstaked {
+        #[ink(topic)]
+        staker: H160,
+        amount: U256,
+        release_at: u64,
+    }
 
-        /// Returns the total token supply
-        #[ink(message)]
-        pub fn total_supply(&self) -> U256 {
-            self.total_supply
-        }
+    /// Event emitted when unbonded tokens are paid out
+    #[ink(event)]
+    pub struct UnbondedWithdrawn {
+        #[ink(topic)]
+        staker: H160,
+        amount: U256,
+    }
 
-        /// Returns the balance of an account
-        #[ink(message)]
-        pub fn balance_of(&self, owner: H160) -> U256 {
-            self.balances.get(owner).unwrap_or(U256::from(0))
-        }
+    /// Event emitted when staking rewards are paid out
+    #[ink(event)]
+    pub struct RewardsClaimed {
+        #[ink(topic)]
+        staker: H160,
+        amount: U256,
+    }
 
-        /// Returns the allowance of a spender for an owner
-        #[ink(message)]
-        pub fn allowance(&self, owner: H160, spender: H160) -> U256 {
-            self.allowances
-                .get((owner, spender))
-                .unwrap_or(U256::from(0))
-        }
+    /// Event emitted when tokens are added to the staking rewards
+    #[ink(event)]
+    pub struct RewardsDeposited {
+        #[ink(topic)]
+        from: H160,
+        amount: U256,
+    }
 
-        /// Transfers tokens from the caller to another account
-        #[ink(message)]
-        pub fn transfer(
-            &mut self,
-            to: H160,
-            value: U256,
-            _data: Vec<u8>,
-        ) -> Result<(), PSP22Error> {
-            let from = self.caller();
-            self.transfer_from_to(from, to, value)
-        }
+    /// Event emitted when native currency is distributed to token holders
+    #[ink(event)]
+    pub struct DividendsDistributed {
+        #[ink(topic)]
+        from: H160,
+        value: U256,
+    }
 
-        /// Transfers tokens from one account to another using allowance
-        #[ink(message)]
-        pub fn transfer_from(
-            &mut self,
-            from: H160,
-            to: H160,
-            value: U256,
-            _data: Vec<u8>,
-        ) -> Result<(), PSP22Error> {
-            let caller = self.caller();
+    /// Event emitted when a holder withdraws their dividends
+    #[ink(event)]
+    pub struct DividendWithdrawn {
+        #[ink(topic)]
+        to: H160,
+        value: U256,
+    }
 
-            // No-op if from and to are the same or value is zero
-            if from == to || value.is_zero() {
-                return Ok(());
-            }
+    /// Event emitted when tokens sent to the contract by mistake are recovered
+    #[ink(event)]
+    pub struct TokensRecovered {
+        #[ink(topic)]
+        token: H160,
+        #[ink(topic)]
+        to: H160,
+        amount: U256,
+    }
 
-            // If caller is not the owner, check allowance
-            if caller != from {
-                let allowance = self.allowances.get((from, caller)).unwrap_or(U256::from(0));
+    /// Event emitted when native currency sent to the contract by mistake is recovered
+    #[ink(event)]
+    pub struct NativeRecovered {
+        #[ink(topic)]
+        to: H160,
+        amount: U256,
+    }
 
-                if allowance < value {
-                    return Err(PSP22Error::InsufficientAllowance);
-                }
+    /// Event emitted when the owner nominates a new owner
+    #[ink(event)]
+    pub struct OwnershipTransferStarted {
+        #[ink(topic)]
+        previous_owner: H160,
+        #[ink(topic)]
+        new_owner: H160,
+    }
 
-                // Decrease allowance
-                self.allowances.insert((from, caller), &(allowance - value));
+    /// Event emitted when the nominated owner accepts ownership
+    #[ink(event)]
+    pub struct OwnershipTransferred {
+        #[ink(topic)]
+        previous_owner: H160,
+        #[ink(topic)]
+        new_owner: H160,
+    }
 
-                self.env().emit_event(Approval {
-                    owner: from,
-                    spender: caller,
-                    value: allowance - value,
-                });
-            }
+    /// Event emitted when the owner withdraws a pending nomination
+    #[ink(event)]
+    pub struct OwnershipTransferCanceled {
+        #[ink(topic)]
+        owner: H160,
+        #[ink(topic)]
+        pending_owner: H160,
+    }
 
-            self.transfer_from_to(from, to, value)
-        }
+    /// Event emitted when an account joins the multisig signer set
+    #[ink(event)]
+    pub struct MultisigSignerAdded {
+        #[ink(topic)]
+        signer: H160,
+    }
 
-        /// Approves a spender to spend tokens on behalf of the caller
-        #[ink(message)]
-        pub fn approve(&mut self, spender: H160, value: U256) -> Result<(), PSP22Error> {
-            let owner = self.caller();
+    /// Event emitted when an account leaves the multisig signer set
+    #[ink(event)]
+    pub struct MultisigSignerRemoved {
+        #[ink(topic)]
+        signer: H160,
+    }
 
-            // No-op if owner and spender are the same
-            if owner == spender {
-                return Ok(());
-            }
+    /// Event emitted when the number of confirmations required changes
+    #[ink(event)]
+    pub struct MultisigThresholdChanged {
+        threshold: u32,
+    }
 
-            self.allowances.insert((owner, spender), &value);
+    /// Event emitted when a signer proposes an administrative action
+    #[ink(event)]
+    pub struct MultisigProposed {
+        #[ink(topic)]
+        id: u64,
+        #[ink(topic)]
+        proposer: H160,
+        action: AdminAction,
+    }
 
-            self.env().emit_event(Approval {
-                owner,
-                spender,
-                value,
-            });
+    /// Event emitted when a signer confirms a proposal
+    #[ink(event)]
+    pub struct MultisigConfirmed {
+        #[ink(topic)]
+        id: u64,
+        #[ink(topic)]
+        signer: H160,
+    }
 
-            Ok(())
-        }
+    /// Event emitted when a signer withdraws their confirmation
+    #[ink(event)]
+    pub struct MultisigConfirmationRevoked {
+        #[ink(topic)]
+        id: u64,
+        #[ink(topic)]
+        signer: H160,
+    }
 
-        /// Increases the allowance of a spender
-        #[ink(message)]
-        pub fn increase_allowance(
-            &mut self,
-            spender: H160,
-            delta_value: U256,
-        ) -> Result<(), PSP22Error> {
-            let owner = self.caller();
+    /// Event emitted when a confirmed proposal is executed
+    #[ink(event)]
+    pub struct MultisigExecuted {
+        #[ink(topic)]
+        id: u64,
+    }
 
-            // No-op if owner and spender are the same or delta_value is zero
-            if owner == spender || delta_value.is_zero() {
-                return Ok(());
-            }
+    /// Event emitted when the timelock minimum delay is set
+    #[ink(event)]
+    pub struct TimelockDelayChanged {
+        min_delay: u64,
+    }
 
-            let current_allowance = self
-                .allowances
-                .get((owner, spender))
-                .unwrap_or(U256::from(0));
-            let new_allowance = current_allowance
-                .checked_add(delta_value)
-                .ok_or(PSP22Error::Overflow)?;
+    /// Event emitted when an administrative action is scheduled
+    #[ink(event)]
+    pub struct OperationScheduled {
+        #[ink(topic)]
+        id: u64,
+        action: AdminAction,
+        eta: u64,
+    }
 
-            self.allowances.insert((owner, spender), &new_allowance);
+    /// Event emitted when a scheduled action is performed
+    #[ink(event)]
+    pub struct OperationExecuted {
+        #[ink(topic)]
+        id: u64,
+    }
 
-            self.env().emit_event(Approval {
-                owner,
-                spender,
-                value: new_allowance,
-            });
+    /// Event emitted when a scheduled action is canceled
+    #[ink(event)]
+    pub struct OperationCanceled {
+        #[ink(topic)]
+        id: u64,
+    }
 
-            Ok(())
-        }
+    /// Event emitted when the governance quorum or voting period changes
+    #[ink(event)]
+    pub struct GovernanceSettingsChanged {
+        quorum: U256,
+        voting_period: u32,
+    }
 
-        /// Decreases the allowance of a spender
-        #[ink(message)]
-        pub fn decrease_allowance(
-            &mut self,
-            spender: H160,
-            delta_value: U256,
-        ) -> Result<(), PSP22Error> {
-            let owner = self.caller();
+    /// Event emitted when the voting power needed to create a proposal changes
+    #[ink(event)]
+    pub struct ProposalThresholdChanged {
+        threshold: U256,
+    }
 
-            // No-op if owner and spender are the same or delta_value is zero
-            if owner == spender || delta_value.is_zero() {
-                return Ok(());
-            }
+    /// Event emitted when a holder submits a governance proposal
+    #[ink(event)]
+    pub struct ProposalCreated {
+        #[ink(topic)]
+        id: u64,
+        #[ink(topic)]
+        proposer: H160,
+        description_hash: H256,
+        actions: Vec<AdminAction>,
+        snapshot_block: u32,
+        vote_end: u32,
+    }
 
-            let current_allowance = self
-                .allowances
-                .get((owner, spender))
-                .unwrap_or(U256::from(0));
+    /// Event emitted when a holder votes on a governance proposal
+    #[ink(event)]
+    pub struct VoteCast {
+        #[ink(topic)]
+        id: u64,
+        #[ink(topic)]
+        voter: H160,
+        support: bool,
+        weight: U256,
+    }
 
-            if current_allowance < delta_value {
-                return Err(PSP22Error::InsufficientAllowance);
+    /// Event emitted when a successful proposal is queued for execution
+    #[ink(event)]
+    pub struct ProposalQueued {
+        #[ink(topic)]
+        id: u64,
+        eta: u64,
+    }
+
+    /// Event emitted when the actions of a proposal are performed
+    #[ink(event)]
+    pub struct ProposalExecuted {
+        #[ink(topic)]
+        id: u64,
+    }
+
+    /// Event emitted when an EIP-3009 authorization is used
+    #[ink(event)]
+    pub struct AuthorizationUsed {
+        #[ink(topic)]
+        authorizer: H160,
+        #[ink(topic)]
+        nonce: H256,
+    }
+
+    /// Event emitted when an EIP-3009 authorization is canceled before use
+    #[ink(event)]
+    pub struct AuthorizationCanceled {
+        #[ink(topic)]
+        authorizer: H160,
+        #[ink(topic)]
+        nonce: H256,
+    }
+
+    /// Event emitted when a holder authorizes an operator
+    #[ink(event)]
+    pub struct AuthorizedOperator {
+        #[ink(topic)]
+        operator: H160,
+        #[ink(topic)]
+        holder: H160,
+    }
+
+    /// Event emitted when a holder revokes an operator
+    #[ink(event)]
+    pub struct RevokedOperator {
+        #[ink(topic)]
+        operator: H160,
+        #[ink(topic)]
+        holder: H160,
+    }
+
+    /// Event emitted when MEME is deposited into the vault for shares
+    #[ink(event)]
+    pub struct VaultDeposit {
+        #[ink(topic)]
+        sender: H160,
+        #[ink(topic)]
+        owner: H160,
+        assets: U256,
+        shares: U256,
+    }
+
+    /// Event emitted when vault shares are redeemed for MEME
+    #[ink(event)]
+    pub struct VaultWithdraw {
+        #[ink(topic)]
+        sender: H160,
+        #[ink(topic)]
+        receiver: H160,
+        #[ink(topic)]
+        owner: H160,
+        assets: U256,
+        shares: U256,
+    }
+
+    /// Event emitted when MEME is added to the vault without minting shares
+    #[ink(event)]
+    pub struct VaultYieldDeposited {
+        #[ink(topic)]
+        from: H160,
+        assets: U256,
+    }
+
+    /// Event emitted when vault shares are minted, burned or transferred
+    #[ink(event)]
+    pub struct ShareTransfer {
+        #[ink(topic)]
+        from: Option<H160>,
+        #[ink(topic)]
+        to: Option<H160>,
+        value: U256,
+    }
+
+    /// Event emitted when a vault share allowance is set
+    #[ink(event)]
+    pub struct ShareApproval {
+        #[ink(topic)]
+        owner: H160,
+        #[ink(topic)]
+        spender: H160,
+        value: U256,
+    }
+
+    /// Event emitted when tokens are burned to be minted on another chain
+    #[ink(event)]
+    pub struct BridgeOut {
+        #[ink(topic)]
+        dest_chain: u64,
+        #[ink(topic)]
+        nonce: u64,
+        #[ink(topic)]
+        sender: H160,
+        recipient: Vec<u8>,
+        amount: U256,
+    }
+
+    /// Event emitted when tokens burned on another chain are minted here
+    #[ink(event)]
+    pub struct BridgeIn {
+        #[ink(topic)]
+        src_chain: u64,
+        #[ink(topic)]
+        nonce: u64,
+        #[ink(topic)]
+        recipient: H160,
+        amount: U256,
+    }
+
+    /// Event emitted when a relayer joins or leaves the bridge relayer set
+    #[ink(event)]
+    pub struct RelayerChanged {
+        #[ink(topic)]
+        relayer: H160,
+        active: bool,
+    }
+
+    /// Event emitted when a chain's inbound rate limit is set
+    #[ink(event)]
+    pub struct BridgeRateLimitChanged {
+        #[ink(topic)]
+        chain: u64,
+        limit: U256,
+        period: u64,
+    }
+
+    /// Defines the storage of your contract.
+    /// Add new fields to the below struct in order
+    /// to add new static storage fields to your contract.
+    ///
+    /// Every message decodes the packed fields (everything but `Lazy` and
+    /// `Mapping`) from the root storage cell before its body runs. Upgraded
+    /// code that adds, removes or retypes a packed field would trap before
+    /// `migrate` gets to run, so the packed fields are frozen and new state goes
+    /// into `Lazy` or `Mapping` fields. Their keys derive from the field name,
+    /// so renaming or retyping one of those needs a `migrate` step.
+    #[ink(storage)]
+    pub struct PspCoin {
+        total_supply: U256,
+        // balance and the other per-account fields, one storage read per account
+        accounts: Mapping<H160, AccountData>,
+        // can owner authorize (allowance > balance)?
+        allowances: Mapping<(H160, H160), Allowance>, // (owner, spender) -> allowance
+        // only decoded by the metadata messages
+        metadata: Lazy<Metadata>,
+        // chain this deployment runs on, as contracts can't read it; part of signed messages.
+        // Unset on deployments without one, which then reject signed messages
+        chain_id: Lazy<u64>,
+        // account allowed to manage the contract configuration
+        owner: H160,
+        // accounts whose signatures are accepted on mint vouchers
+        voucher_signers: Mapping<H160, ()>,
+        used_voucher_nonces: Mapping<u128, ()>,
+        // layout version of the data currently in storage
+        storage_version: u32,
+        // wrapped native currency mode: supply only changes through deposit/withdraw
+        wrapped: bool,
+        // tokens held by the contract itself on behalf of escrows and similar
+        custody: U256,
+        escrows: Mapping<u64, Escrow>,
+        next_escrow_id: u64,
+        streams: Mapping<u64, Stream>,
+        next_stream_id: u64,
+        stakes: Mapping<H160, StakeInfo>,
+        total_staked: U256,
+        // rewards per staked token, scaled by `REWARD_PRECISION`
+        reward_per_token: U256,
+        // tokens minted as staking rewards per millisecond
+        reward_rate: U256,
+        last_reward_update: u64,
+        unbonding_period: u64,
+        // native currency distributed per token, scaled by 2^DIVIDEND_MAGNITUDE_BITS
+        dividend_per_share: U256,
+        // native currency distributed but not withdrawn yet
+        dividend_reserve: U256,
+        // nominated owner that still has to accept ownership
+        pending_owner: Option<H160>,
+        // M-of-N signers in control once the contract owns itself
+        multisig_signers: Mapping<H160, ()>,
+        multisig_signer_count: u32,
+        multisig_threshold: u32,
+        multisig_proposals: Mapping<u64, MultisigProposal>,
+        next_multisig_proposal_id: u64,
+        // Delay imposed on privileged actions
+        timelock_enabled: bool,
+        timelock_min_delay: u64,
+        timelock_operations: Mapping<u64, TimelockOperation>,
+        next_timelock_operation_id: u64,
+        // ids of scheduled operations, indexed for enumeration
+        pending_operation_count: Lazy<u32>,
+        pending_operation_ids: Mapping<u32, u64>,
+        pending_operation_indices: Mapping<u64, u32>,
+        // Balance history and token-weighted governance
+        balance_checkpoints: Mapping<(H160, u32), Checkpoint>,
+        balance_checkpoint_counts: Mapping<H160, u32>,
+        // block balance history starts at, set when governance is first configured
+        checkpoint_start_block: Lazy<u32>,
+        governance_quorum: U256,
+        governance_voting_period: u32,
+        // voting power a proposer needs at the snapshot, unset means any
+        governance_proposal_threshold: Lazy<U256>,
+        governance_proposals: Mapping<u64, GovernanceProposal>,
+        governance_votes: Mapping<(u64, H160), bool>,
+        next_governance_proposal_id: u64,
+        // Accounts with a non-zero balance, indexed for enumeration
+        holder_count: u32,
+        holders: Mapping<u32, H160>,
+        holder_indices: Mapping<H160, u32>,
+        // EIP-3009 nonces used or canceled per authorizer
+        authorization_states: Mapping<(H160, H256), ()>,
+        // Operators with full control over holders' tokens
+        operators: Mapping<(H160, H160), ()>,
+        default_operators: Mapping<H160, ()>,
+        default_operator_list: Lazy<Vec<H160>>,
+        revoked_default_operators: Mapping<(H160, H160), ()>,
+        // ERC-4626 vault over MEME with its own share ledger
+        vault_assets: U256,
+        vault_total_shares: U256,
+        vault_shares: Mapping<H160, U256>,
+        vault_share_allowances: Mapping<(H160, H160), U256>,
+        // Lock/mint bridge with M-of-N relayer attestations
+        bridge_relayers: Mapping<H160, ()>,
+        bridge_relayer_count: u32,
+        bridge_threshold: u32,
+        bridge_rate_limits: Mapping<u64, BridgeRateLimit>,
+        bridge_outbound_nonces: Mapping<u64, u64>,
+        bridge_processed: Mapping<(u64, u64), ()>,
+    }
+
+    impl PspCoin {
+        /// Constructor that initializes a memecoin with zero supply
+        ///
+        /// Tokens deployed without a chain id reject signed messages; use one of
+        /// the `*_with_chain_id` constructors to accept them.
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            let mut metadata = Lazy::new();
+            metadata.set(&Metadata {
+                name: String::from("MemeCoin"),
+                symbol: String::from("MEME"),
+                decimals: 18,
+            });
+
+            Self {
+                total_supply: U256::from(0),
+                accounts: Mapping::default(),
+                allowances: Mapping::default(),
+                metadata,
+                chain_id: Lazy::new(),
+                owner: Self::env().caller(),
+                voucher_signers: Mapping::default(),
+                used_voucher_nonces: Mapping::default(),
+                storage_version: STORAGE_VERSION,
+                wrapped: false,
+                custody: U256::from(0),
+                escrows: Mapping::default(),
+                next_escrow_id: 0,
+                streams: Mapping::default(),
+                next_stream_id: 0,
+                stakes: Mapping::default(),
+                total_staked: U256::from(0),
+                reward_per_token: U256::from(0),
+                reward_rate: U256::from(0),
+                last_reward_update: Self::env().block_timestamp(),
+                unbonding_period: 0,
+                dividend_per_share: U256::from(0),
+                dividend_reserve: U256::from(0),
+                pending_owner: None,
+                multisig_signers: Mapping::default(),
+                multisig_signer_count: 0,
+                multisig_threshold: 0,
+                multisig_proposals: Mapping::default(),
+                next_multisig_proposal_id: 0,
+                timelock_enabled: false,
+                timelock_min_delay: 0,
+                timelock_operations: Mapping::default(),
+                next_timelock_operation_id: 0,
+                pending_operation_count: Lazy::default(),
+                pending_operation_ids: Mapping::default(),
+                pending_operation_indices: Mapping::default(),
+                balance_checkpoints: Mapping::default(),
+                balance_checkpoint_counts: Mapping::default(),
+                checkpoint_start_block: Lazy::default(),
+                governance_quorum: U256::from(0),
+                governance_voting_period: 0,
+                governance_proposal_threshold: Lazy::default(),
+                governance_proposals: Mapping::default(),
+                governance_votes: Mapping::default(),
+                next_governance_proposal_id: 0,
+                holder_count: 0,
+                holders: Mapping::default(),
+                holder_indices: Mapping::default(),
+                authorization_states: Mapping::default(),
+                operators: Mapping::default(),
+                default_operators: Mapping::default(),
+                default_operator_list: Lazy::default(),
+                revoked_default_operators: Mapping::default(),
+                vault_assets: U256::from(0),
+                vault_total_shares: U256::from(0),
+                vault_shares: Mapping::default(),
+                vault_share_allowances: Mapping::default(),
+                bridge_relayers: Mapping::default(),
+                bridge_relayer_count: 0,
+                bridge_threshold: 0,
+                bridge_rate_limits: Mapping::default(),
+                bridge_outbound_nonces: Mapping::default(),
+                bridge_processed: Mapping::default(),
             }
+        }
 
-            let new_allowance = current_allowance - delta_value;
-            self.allowances.insert((owner, spender), &new_allowance);
+        /// Constructor that initializes a memecoin with initial supply
+        #[ink(constructor)]
+        pub fn new_with_supply(total_supply: U256) -> Self {
+            let caller_h160 = Self::env().caller();
+            // Constructors can't return a `PSP22Error`, so refuse to deploy instead
+            assert!(!caller_h160.is_zero(), "ZeroAddress");
+
+            let mut instance = Self::new();
+            instance.set_account(
+                caller_h160,
+                &AccountData {
+                    balance: total_supply,
+                    ..Default::default()
+                },
+            );
+            instance.total_supply = total_supply;
+            if !total_supply.is_zero() {
+                instance.add_holder(caller_h160);
+            }
+            instance
+        }
 
-            self.env().emit_event(Approval {
-                owner,
-                spender,
-                value: new_allowance,
+        /// Constructor that initializes a wrapped version of the chain's native currency
+        ///
+        /// Tokens are only minted by `deposit` and burned by `withdraw`, so the
+        /// total supply always equals the native currency held for holders.
+        #[ink(constructor)]
+        pub fn new_wrapped(name: String, symbol: String, decimals: u8) -> Self {
+            let mut instance = Self::new();
+            instance.metadata.set(&Metadata {
+                name,
+                symbol,
+                decimals,
             });
+            instance.wrapped = true;
+            instance
+        }
 
-            Ok(())
+        /// Constructor that mints `total_supply` to the caller and trusts `default_operators`
+        ///
+        /// Default operators can move tokens of every holder, unless the holder
+        /// revokes them with `revoke_operator`.
+        #[ink(constructor)]
+        pub fn new_with_operators(total_supply: U256, default_operators: Vec<H160>) -> Self {
+            let mut instance = Self::new_with_supply(total_supply);
+            for operator in &default_operators {
+                assert!(!operator.is_zero(), "ZeroAddress");
+                instance.default_operators.insert(*operator, &());
+            }
+            instance.default_operator_list.set(&default_operators);
+            instance
         }
 
-        // PSP22 Metadata Functions
+        /// Constructor like `new_with_supply` that accepts messages signed for chain `chain_id`
+        ///
+        /// `chain_id` goes into signed messages (EIP-3009 authorizations and
+        /// bridge attestations), so signatures for one chain can't be replayed
+        /// on a deployment at the same address on another chain.
+        #[ink(constructor)]
+        pub fn new_with_chain_id(total_supply: U256, chain_id: u64) -> Self {
+            Self::new_with_supply(total_supply).with_chain_id(chain_id)
+        }
 
-        /// Returns the token name
-        #[ink(message)]
-        pub fn name(&self) -> Option<String> {
-            Some(self.metadata.0.clone())
+        /// Constructor like `new_wrapped` that accepts messages signed for chain `chain_id`
+        #[ink(constructor)]
+        pub fn new_wrapped_with_chain_id(
+            name: String,
+            symbol: String,
+            decimals: u8,
+            chain_id: u64,
+        ) -> Self {
+            Self::new_wrapped(name, symbol, decimals).with_chain_id(chain_id)
         }
 
-        /// Returns the token symbol
-        #[ink(message)]
-        pub fn symbol(&self) -> Option<String> {
-            Some(self.metadata.1.clone())
+        /// Constructor like `new_with_operators` that accepts messages signed for chain `chain_id`
+        #[ink(constructor)]
+        pub fn new_with_operators_and_chain_id(
+            total_supply: U256,
+            default_operators: Vec<H160>,
+            chain_id: u64,
+        ) -> Self {
+            Self::new_with_operators(total_supply, default_operators).with_chain_id(chain_id)
         }
 
-        /// Returns the token decimals
-        #[ink(message)]
-        pub fn decimals(&self) -> u8 {
-            self.metadata.2
+        /// Binds signed messages to `chain_id`, which must not be zero
+        fn with_chain_id(mut self, chain_id: u64) -> Self {
+            // Zero is no chain id, so signatures for it would be valid everywhere
+            assert!(chain_id != 0, "ChainIdNotSet");
+            self.chain_id.set(&chain_id);
+            self
         }
 
-        // PSP22 Mintable Functions
+        /// Helper function to get the caller as H160
+        fn caller(&self) -> H160 {
+            self.env().caller()
+        }
 
-        /// Mints new tokens to the caller's account
-        #[ink(message)]
-        pub fn mint(&mut self, value: U256) -> Result<(), PSP22Error> {
-            // No-op if value is zero
-            if value.is_zero() {
-                return Ok(());
+        /// Loads the per-account record, defaulting for unknown accounts
+        fn account(&self, owner: H160) -> AccountData {
+            self.accounts.get(owner).unwrap_or_default()
+        }
+
+        /// Dividends `account` has earned since it was last settled
+        fn unsettled_dividends(&self, owner: H160, account: &AccountData) -> Option<U256> {
+            // Tokens in the contract's custody belong to escrows, stakes and
+            // similar, so the contract itself doesn't earn dividends
+            if owner == self.env().address() {
+                return Some(U256::from(0));
             }
 
-            let caller = self.caller();
-            let balance = self.balances.get(caller).unwrap_or(U256::from(0));
+            account
+                .balance
+                .checked_mul(self.dividend_per_share - account.dividend_per_share_paid)
+                .map(|magnified| magnified >> DIVIDEND_MAGNITUDE_BITS)
+        }
 
-            // Check for overflow
-            let new_balance = balance.checked_add(value).ok_or(PSP22Error::Overflow)?;
-            let new_supply = self
-                .total_supply
-                .checked_add(value)
+        /// Loads the per-account record with its dividends settled up to now
+        ///
+        /// Must be used for every record whose balance is about to change, so
+        /// dividends are always earned on the balance held at distribution time.
+        fn settled_account(&self, owner: H160) -> Result<AccountData, PSP22Error> {
+            let mut account = self.account(owner);
+
+            let earned = self
+                .unsettled_dividends(owner, &account)
+                .ok_or(PSP22Error::Overflow)?;
+            account.dividend_credit = account
+                .dividend_credit
+                .checked_add(earned)
                 .ok_or(PSP22Error::Overflow)?;
+            account.dividend_per_share_paid = self.dividend_per_share;
 
-            self.balances.insert(caller, &new_balance);
-            self.total_supply = new_supply;
+            Ok(account)
+        }
 
-            self.env().emit_event(Transfer {
-                from: None,
-                to: Some(caller),
-                value,
-            });
+        /// Stores the per-account record, removing it once it holds nothing
+        /// so the storage deposit is refunded
+        fn set_account(&mut self, owner: H160, account: &AccountData) {
+            self.write_checkpoint(owner, account.balance);
 
-            Ok(())
+            if account.is_empty() {
+                self.accounts.remove(owner);
+            } else {
+                self.accounts.insert(owner, account);
+            }
         }
 
-        // PSP22 Burnable Functions
+        /// Records `balance` as the balance of `owner` at the end of the current block
+        ///
+        /// History is only kept once governance is configured, so until then
+        /// balance writes cost no extra reads and leave no entries behind.
+        fn write_checkpoint(&mut self, owner: H160, balance: U256) {
+            if self.governance_voting_period == 0 {
+                return;
+            }
 
-        /// Burns tokens from the caller's account
-        #[ink(message)]
-        pub fn burn(&mut self, value: U256) -> Result<(), PSP22Error> {
-            // No-op if value is zero
-            if value.is_zero() {
-                return Ok(());
+            let block = self.env().block_number();
+            let mut count = self.balance_checkpoint_counts.get(owner).unwrap_or(0);
+            let last = match count.checked_sub(1) {
+                Some(index) => self.balance_checkpoints.get((owner, index)).unwrap_or_default(),
+                // Nothing recorded yet, so the stored balance was held since history started
+                None => Checkpoint {
+                    block: self.checkpoint_start_block.get().unwrap_or(block),
+                    balance: self.account(owner).balance,
+                },
+            };
+
+            if last.balance == balance {
+                return;
+            }
+            if count > 0 && last.block == block {
+                self.balance_checkpoints
+                    .insert((owner, count - 1), &Checkpoint { block, balance });
+                return;
+            }
+            if count == 0 && !last.balance.is_zero() && last.block < block {
+                self.balance_checkpoints.insert((owner, 0), &last);
+                count = 1;
             }
 
-            let caller = self.caller();
-            let balance = self.balances.get(caller).unwrap_or(U256::from(0));
+            self.balance_checkpoints
+                .insert((owner, count), &Checkpoint { block, balance });
+            self.balance_checkpoint_counts.insert(owner, &(count + 1));
+        }
 
-            if balance < value {
-                return Err(PSP22Error::InsufficientBalance);
+        /// Loads the allowance entry, ignoring whether it has expired
+        fn allowance_entry(&self, owner: H160, spender: H160) -> Allowance {
+            self.allowances.get((owner, spender)).unwrap_or_default()
+        }
+
+        /// Stores an allowance, removing the entry when it drops to zero
+        fn set_allowance(&mut self, owner: H160, spender: H160, allowance: &Allowance) {
+            if allowance.value.is_zero() {
+                self.allowances.remove((owner, spender));
+            } else {
+                self.allowances.insert((owner, spender), allowance);
             }
+        }
 
-            self.balances.insert(caller, &(balance - value));
-            self.total_supply = self.total_supply - value;
+        /// Grants `spender` an allowance of `value`, optionally expiring at `expires_at`
+        fn approve_until(
+            &mut self,
+            owner: H160,
+            spender: H160,
+            value: U256,
+            expires_at: Option<u64>,
+        ) {
+            self.set_allowance(owner, spender, &Allowance { value, expires_at });
 
-            self.env().emit_event(Transfer {
-                from: Some(caller),
-                to: None,
+            self.env().emit_event(Approval {
+                owner,
+                spender,
                 value,
             });
+        }
 
+        /// Fails with `ZeroAddress` if `account` is the zero address
+        ///
+        /// Tokens sent or allowances granted to it could never be used again;
+        /// tokens are destroyed through `burn` instead.
+        fn ensure_not_zero(&self, account: H160) -> Result<(), PSP22Error> {
+            if account.is_zero() {
+                return Err(PSP22Error::ZeroAddress);
+            }
             Ok(())
         }
-    }
 
-    #[cfg(test)]
-    mod tests {
-        use super::*;
-        use ink::env::test;
+        /// Fails if the supply is backed by native currency and may not be changed freely
+        fn ensure_not_wrapped(&self) -> Result<(), PSP22Error> {
+            if self.wrapped {
+                return Err(PSP22Error::WrappedMode);
+            }
+            Ok(())
+        }
 
-        fn default_accounts() -> test::DefaultAccounts {
-            test::default_accounts()
+        /// Fails unless the contract was deployed with a chain id to sign messages for
+        fn ensure_chain_id(&self) -> Result<(), PSP22Error> {
+            if self.chain_id.get().is_none() {
+                return Err(PSP22Error::ChainIdNotSet);
+            }
+            Ok(())
         }
 
-        fn set_caller(caller: H160) {
-            test::set_caller(caller);
+        /// Fails while storage still has the layout of older code, until `migrate` ran
+        fn ensure_migrated(&self) -> Result<(), PSP22Error> {
+            if self.storage_version != STORAGE_VERSION {
+                return Err(PSP22Error::MigrationRequired);
+            }
+            Ok(())
         }
 
-        #[ink::test]
-        fn new_works() {
-            let token = PspCoin::new();
-            assert_eq!(token.total_supply(), U256::from(0));
-            assert_eq!(token.name(), Some(String::from("MemeCoin")));
-            assert_eq!(token.symbol(), Some(String::from("MEME")));
-            assert_eq!(token.decimals(), 18);
+        /// Like `ensure_migrated`, but lets through actions that finish or back out of an upgrade
+        fn ensure_migrated_for(&self, action: &AdminAction) -> Result<(), PSP22Error> {
+            if action.runs_before_migration() {
+                return Ok(());
+            }
+            self.ensure_migrated()
+        }
+
+        /// Fails unless the contract wraps native currency
+        fn ensure_wrapped(&self) -> Result<(), PSP22Error> {
+            if !self.wrapped {
+                return Err(PSP22Error::NotWrapped);
+            }
+            Ok(())
+        }
+
+        /// Fails unless the caller is the contract owner
+        fn ensure_owner(&self) -> Result<(), PSP22Error> {
+            if self.caller() != self.owner {
+                return Err(PSP22Error::Unauthorized);
+            }
+            Ok(())
+        }
+
+        /// Recovers the Ethereum-style address that produced `signature` over `message_hash`
+        fn recover_signer(
+            &self,
+            message_hash: &[u8; 32],
+            signature: &[u8; 65],
+        ) -> Result<H160, PSP22Error> {
+            let invalid = || PSP22Error::InvalidSignature;
+
+            let mut public_key = [0u8; 33];
+            self.env()
+                .ecdsa_recover(signature, message_hash, &mut public_key)
+                .map_err(|_| invalid())?;
+
+            let mut address = [0u8; 20];
+            self.env()
+                .ecdsa_to_eth_address(&public_key, &mut address)
+                .map_err(|_| invalid())?;
+
+            Ok(H160::from(address))
+        }
+
+        /// Hash a voucher signer has to sign to authorize minting `amount` to `to`
+        ///
+        /// The type tag keeps it apart from other signed messages, and the
+        /// contract address and chain id stop vouchers from being replayed on
+        /// another deployment, including one at the same address on another chain.
+        fn voucher_hash(&self, to: H160, amount: U256, nonce: u128, expiry: u64) -> [u8; 32] {
+            let mut output = [0u8; 32];
+            ink::env::hash_encoded::<Keccak256, _>(
+                &(
+                    Self::keccak256(MINT_VOUCHER_TYPE.as_bytes()),
+                    self.env().address(),
+                    self.chain_id(),
+                    to,
+                    amount,
+                    nonce,
+                    expiry,
+                ),
+                &mut output,
+            );
+            output
+        }
+
+        /// Keccak-256 hash of `input`
+        fn keccak256(input: &[u8]) -> [u8; 32] {
+            let mut output = [0u8; 32];
+            ink::env::hash_bytes::<Keccak256>(input, &mut output);
+            output
+        }
+
+        /// Left-pads `account` to a 32-byte ABI word
+        fn address_word(account: H160) -> [u8; 32] {
+            let mut word = [0u8; 32];
+            word[12..].copy_from_slice(account.as_bytes());
+            word
+        }
+
+        /// EIP-712 digest of a struct of `type_string` with ABI-encoded `fields`
+        ///
+        /// The domain is the token name, version "1", the configured chain id and
+        /// this contract's address.
+        fn eip712_digest(&self, type_string: &str, fields: &[[u8; 32]]) -> [u8; 32] {
+            let name = self
+                .metadata
+                .get()
+                .map(|metadata| metadata.name)
+                .unwrap_or_default();
+
+            let mut domain = Vec::with_capacity(5 * 32);
+            domain.extend_from_slice(&Self::keccak256(EIP712_DOMAIN_TYPE.as_bytes()));
+            domain.extend_from_slice(&Self::keccak256(name.as_bytes()));
+            domain.extend_from_slice(&Self::keccak256(b"1"));
+            domain.extend_from_slice(&U256::from(self.chain_id()).to_big_endian());
+            domain.extend_from_slice(&Self::address_word(self.env().address()));
+
+            let mut encoded = Vec::with_capacity((fields.len() + 1) * 32);
+            encoded.extend_from_slice(&Self::keccak256(type_string.as_bytes()));
+            for field in fields {
+                encoded.extend_from_slice(field);
+            }
+
+            let mut message = Vec::with_capacity(2 + 2 * 32);
+            message.extend_from_slice(b"\x19\x01");
+            message.extend_from_slice(&Self::keccak256(&domain));
+            message.extend_from_slice(&Self::keccak256(&encoded));
+            Self::keccak256(&message)
+        }
+
+        /// Digest `from` signs to allow moving `value` to `to` within the validity window
+        #[allow(clippy::too_many_arguments)]
+        fn authorization_digest(
+            &self,
+            type_string: &str,
+            from: H160,
+            to: H160,
+            value: U256,
+            valid_after: u64,
+            valid_before: u64,
+            nonce: H256,
+        ) -> [u8; 32] {
+            self.eip712_digest(
+                type_string,
+                &[
+                    Self::address_word(from),
+                    Self::address_word(to),
+                    value.to_big_endian(),
+                    U256::from(valid_after).to_big_endian(),
+                    U256::from(valid_before).to_big_endian(),
+                    nonce.0,
+                ],
+            )
+        }
+
+        /// Recovers the signer of `message_hash` from an Ethereum `(v, r, s)` signature
+        fn recover_vrs(
+            &self,
+            message_hash: &[u8; 32],
+            v: u8,
+            r: H256,
+            s: H256,
+        ) -> Result<H160, PSP22Error> {
+            let mut signature = [0u8; 65];
+            signature[..32].copy_from_slice(r.as_bytes());
+            signature[32..64].copy_from_slice(s.as_bytes());
+            // Wallets sign with v = 27 or 28, the recovery id is 0 or 1
+            signature[64] = if v >= 27 { v - 27 } else { v };
+            self.recover_signer(message_hash, &signature)
+        }
+
+        /// Checks and consumes an EIP-3009 authorization, then moves the tokens
+        #[allow(clippy::too_many_arguments)]
+        fn use_authorization(
+            &mut self,
+            type_string: &str,
+            from: H160,
+            to: H160,
+            value: U256,
+            valid_after: u64,
+            valid_before: u64,
+            nonce: H256,
+            v: u8,
+            r: H256,
+            s: H256,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_chain_id()?;
+            // EIP-3009 windows are in seconds, block timestamps in milliseconds
+            let now = self.env().block_timestamp() / 1_000;
+            if now <= valid_after {
+                return Err(PSP22Error::AuthorizationNotYetValid);
+            }
+            if now >= valid_before {
+                return Err(PSP22Error::AuthorizationExpired);
+            }
+            if self.authorization_states.contains((from, nonce)) {
+                return Err(PSP22Error::AuthorizationAlreadyUsed);
+            }
+
+            let digest = self.authorization_digest(
+                type_string,
+                from,
+                to,
+                value,
+                valid_after,
+                valid_before,
+                nonce,
+            );
+            if self.recover_vrs(&digest, v, r, s)? != from {
+                return Err(PSP22Error::InvalidSignature);
+            }
+
+            self.authorization_states.insert((from, nonce), &());
+            self.env().emit_event(AuthorizationUsed {
+                authorizer: from,
+                nonce,
+            });
+
+            self.transfer_from_to(from, to, value)
+        }
+
+        /// Hash relayers sign to attest that `amount` was burned on `src_chain` for `recipient`
+        ///
+        /// Binds the attestation to this contract's address and chain id, so it
+        /// can't be replayed on a deployment at the same address on another
+        /// chain that also accepts transfers from `src_chain`.
+        fn bridge_message_hash(
+            &self,
+            src_chain: u64,
+            nonce: u64,
+            recipient: H160,
+            amount: U256,
+        ) -> [u8; 32] {
+            let mut output = [0u8; 32];
+            ink::env::hash_encoded::<Keccak256, _>(
+                &(
+                    self.env().address(),
+                    src_chain,
+                    self.chain_id(),
+                    nonce,
+                    recipient,
+                    amount,
+                ),
+                &mut output,
+            );
+            output
+        }
+
+        /// Invokes a receiver hook on `target` and checks it returned `selector`
+        ///
+        /// The hook is called as `selector(args...) -> [u8; 4]`; a tuple of
+        /// arguments SCALE-encodes exactly like pushing them one by one. Any
+        /// failure to call the target, decode its answer or a wrong answer is
+        /// a rejection.
+        ///
+        /// The hook may call back into this contract, e.g. a spender pulling its
+        /// allowance with `transfer_from`. Such calls decode the root fields
+        /// from storage, so our copy is written before the hook and reloaded
+        /// after it; otherwise they'd see stale fields, and their changes would
+        /// be overwritten when this message returns.
+        fn call_receiver<Args>(
+            &mut self,
+            target: H160,
+            selector: [u8; 4],
+            args: Args,
+        ) -> Result<(), PSP22Error>
+        where
+            Args: scale::Encode,
+        {
+            let root_key = <Self as ink::storage::traits::StorageKey>::KEY;
+            ink::env::set_contract_storage(&root_key, &*self);
+
+            let result = build_call::<Environment>()
+                .call(target)
+                .call_flags(CallFlags::ALLOW_REENTRY)
+                .exec_input(ExecutionInput::new(Selector::new(selector)).push_arg(args))
+                .returns::<[u8; 4]>()
+                .try_invoke();
+
+            if let Ok(Some(root)) = ink::env::get_contract_storage::<_, Self>(&root_key) {
+                *self = root;
+            }
+
+            match result {
+                Ok(Ok(magic)) if magic == selector => Ok(()),
+                _ => Err(PSP22Error::ReceiverRejected),
+            }
+        }
+
+        /// Internal mint function
+        fn mint_to(&mut self, to: H160, value: U256) -> Result<(), PSP22Error> {
+            self.ensure_not_zero(to)?;
+
+            // No-op if value is zero
+            if value.is_zero() {
+                return Ok(());
+            }
+
+            let mut account = self.settled_account(to)?;
+
+            // Check for overflow
+            account.balance = account
+                .balance
+                .checked_add(value)
+                .ok_or(PSP22Error::Overflow)?;
+            let new_supply = self
+                .total_supply
+                .checked_add(value)
+                .ok_or(PSP22Error::Overflow)?;
+
+            self.set_account(to, &account);
+            self.total_supply = new_supply;
+            if account.balance == value {
+                self.add_holder(to);
+            }
+
+            self.env().emit_event(Transfer {
+                from: None,
+                to: Some(to),
+                value,
+            });
+
+            Ok(())
+        }
+
+        /// Appends `holder` to the holder index once its balance becomes non-zero
+        ///
+        /// The contract's own balance is custody for escrows and similar, so
+        /// like for dividends it doesn't count as a holder.
+        fn add_holder(&mut self, holder: H160) {
+            if holder == self.env().address() {
+                return;
+            }
+
+            let index = self.holder_count;
+            self.holders.insert(index, &holder);
+            self.holder_indices.insert(holder, &index);
+            self.holder_count = index + 1;
+        }
+
+        /// Drops `holder` from the holder index once its balance drops to zero
+        ///
+        /// The last holder moves into the freed slot, so listing order isn't stable.
+        fn remove_holder(&mut self, holder: H160) {
+            if holder == self.env().address() {
+                return;
+            }
+            let Some(index) = self.holder_indices.take(holder) else {
+                return;
+            };
+
+            let last = self.holder_count - 1;
+            if index != last {
+                if let Some(moved) = self.holders.get(last) {
+                    self.holders.insert(index, &moved);
+                    self.holder_indices.insert(moved, &index);
+                }
+            }
+            self.holders.remove(last);
+            self.holder_count = last;
+        }
+
+        /// Internal burn function
+        fn burn_from(&mut self, from: H160, value: U256) -> Result<(), PSP22Error> {
+            // No-op if value is zero
+            if value.is_zero() {
+                return Ok(());
+            }
+
+            let mut account = self.settled_account(from)?;
+
+            if account.balance < value {
+                return Err(PSP22Error::InsufficientBalance);
+            }
+
+            account.balance = account.balance - value;
+            self.set_account(from, &account);
+            self.total_supply = self.total_supply - value;
+            if account.balance.is_zero() {
+                self.remove_holder(from);
+            }
+
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to: None,
+                value,
+            });
+
+            Ok(())
+        }
+
+        /// Internal transfer function
+        fn transfer_from_to(
+            &mut self,
+            from: H160,
+            to: H160,
+            value: U256,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_not_zero(to)?;
+
+            // No-op if from and to are the same or value is zero
+            if from == to || value.is_zero() {
+                return Ok(());
+            }
+
+            let mut from_account = self.settled_account(from)?;
+
+            if from_account.balance < value {
+                return Err(PSP22Error::InsufficientBalance);
+            }
+
+            let mut to_account = self.settled_account(to)?;
+
+            // Check for overflow
+            to_account.balance = to_account
+                .balance
+                .checked_add(value)
+                .ok_or(PSP22Error::Overflow)?;
+            from_account.balance = from_account.balance - value;
+
+            self.set_account(from, &from_account);
+            self.set_account(to, &to_account);
+            if from_account.balance.is_zero() {
+                self.remove_holder(from);
+            }
+            if to_account.balance == value {
+                self.add_holder(to);
+            }
+
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to: Some(to),
+                value,
+            });
+
+            Ok(())
+        }
+
+        /// Moves `value` tokens from `from` into the contract's custody
+        fn lock_in_custody(&mut self, from: H160, value: U256) -> Result<(), PSP22Error> {
+            let contract = self.env().address();
+            self.transfer_from_to(from, contract, value)?;
+            self.custody = self
+                .custody
+                .checked_add(value)
+                .ok_or(PSP22Error::Overflow)?;
+            Ok(())
+        }
+
+        /// Pays `value` tokens out of the contract's custody to `to`
+        fn release_from_custody(&mut self, to: H160, value: U256) -> Result<(), PSP22Error> {
+            let contract = self.env().address();
+            self.custody = self
+                .custody
+                .checked_sub(value)
+                .ok_or(PSP22Error::Overflow)?;
+            self.transfer_from_to(contract, to, value)
+        }
+
+        /// Loads an escrow that can still be released or refunded
+        fn open_escrow_by_id(&self, id: u64) -> Result<Escrow, PSP22Error> {
+            let escrow = self
+                .escrows
+                .get(id)
+                .ok_or(PSP22Error::EscrowNotFound)?;
+
+            if escrow.status != EscrowStatus::Open {
+                return Err(PSP22Error::EscrowClosed);
+            }
+
+            Ok(escrow)
+        }
+
+        /// Loads a stream or fails with `StreamNotFound`
+        fn stream_by_id(&self, id: u64) -> Result<Stream, PSP22Error> {
+            self.streams
+                .get(id)
+                .ok_or(PSP22Error::StreamNotFound)
+        }
+
+        /// Stores a staker's record, removing it once it holds nothing
+        fn set_stake(&mut self, staker: H160, stake: &StakeInfo) {
+            if stake.is_empty() {
+                self.stakes.remove(staker);
+            } else {
+                self.stakes.insert(staker, stake);
+            }
+        }
+
+        /// Reward-per-token accumulator including emissions not accrued yet
+        fn current_reward_per_token(&self) -> U256 {
+            let elapsed = self
+                .env()
+                .block_timestamp()
+                .saturating_sub(self.last_reward_update);
+            if self.total_staked.is_zero() {
+                return self.reward_per_token;
+            }
+
+            let emitted = self.reward_rate.saturating_mul(U256::from(elapsed));
+            self.reward_per_token.saturating_add(
+                emitted.saturating_mul(U256::from(REWARD_PRECISION)) / self.total_staked,
+            )
+        }
+
+        /// Mints the emissions since the last update into custody and adds them
+        /// to the reward-per-token accumulator
+        fn accrue_rewards(&mut self) -> Result<(), PSP22Error> {
+            let now = self.env().block_timestamp();
+            let elapsed = now.saturating_sub(self.last_reward_update);
+            self.last_reward_update = now;
+
+            if self.total_staked.is_zero() {
+                return Ok(());
+            }
+
+            let emitted = self
+                .reward_rate
+                .checked_mul(U256::from(elapsed))
+                .ok_or(PSP22Error::Overflow)?;
+            if emitted.is_zero() {
+                return Ok(());
+            }
+
+            let contract = self.env().address();
+            self.mint_to(contract, emitted)?;
+            self.custody = self
+                .custody
+                .checked_add(emitted)
+                .ok_or(PSP22Error::Overflow)?;
+
+            self.add_to_reward_per_token(emitted)
+        }
+
+        /// Spreads `amount` reward tokens over the currently staked tokens
+        fn add_to_reward_per_token(&mut self, amount: U256) -> Result<(), PSP22Error> {
+            let increase = amount
+                .checked_mul(U256::from(REWARD_PRECISION))
+                .ok_or(PSP22Error::Overflow)?
+                / self.total_staked;
+            self.reward_per_token = self
+                .reward_per_token
+                .checked_add(increase)
+                .ok_or(PSP22Error::Overflow)?;
+            Ok(())
+        }
+
+        /// Accrues pending emissions and credits `staker` with the rewards
+        /// earned since their last update
+        fn update_stake(&mut self, staker: H160) -> Result<StakeInfo, PSP22Error> {
+            self.accrue_rewards()?;
+
+            let mut stake = self.stakes.get(staker).unwrap_or_default();
+            let earned = stake
+                .staked
+                .checked_mul(self.reward_per_token - stake.reward_per_token_paid)
+                .ok_or(PSP22Error::Overflow)?
+                / U256::from(REWARD_PRECISION);
+            stake.rewards = stake
+                .rewards
+                .checked_add(earned)
+                .ok_or(PSP22Error::Overflow)?;
+            stake.reward_per_token_paid = self.reward_per_token;
+
+            Ok(stake)
+        }
+
+        /// Fails unless the multisig controls the contract and the caller is one of its signers
+        fn ensure_multisig_signer(&self) -> Result<H160, PSP22Error> {
+            let caller = self.caller();
+            if !self.is_multisig_enabled() || !self.multisig_signers.contains(caller) {
+                return Err(PSP22Error::Unauthorized);
+            }
+            Ok(caller)
+        }
+
+        /// Loads a pending multisig proposal or fails with `ProposalNotFound`
+        fn multisig_proposal_by_id(&self, id: u64) -> Result<MultisigProposal, PSP22Error> {
+            self.multisig_proposals
+                .get(id)
+                .ok_or(PSP22Error::ProposalNotFound)
+        }
+
+        /// Adds `signer` to the multisig signer set
+        fn add_multisig_signer(&mut self, signer: H160) -> Result<(), PSP22Error> {
+            if self.multisig_signers.contains(signer) {
+                return Err(PSP22Error::AlreadySigner);
+            }
+
+            self.multisig_signers.insert(signer, &());
+            self.multisig_signer_count = self
+                .multisig_signer_count
+                .checked_add(1)
+                .ok_or(PSP22Error::Overflow)?;
+
+            self.env().emit_event(MultisigSignerAdded { signer });
+
+            Ok(())
+        }
+
+        /// Sets how many signers must confirm a proposal, between 1 and the signer count
+        fn set_multisig_threshold(&mut self, threshold: u32) -> Result<(), PSP22Error> {
+            if threshold == 0 || threshold > self.multisig_signer_count {
+                return Err(PSP22Error::InvalidThreshold);
+            }
+
+            self.multisig_threshold = threshold;
+
+            self.env().emit_event(MultisigThresholdChanged { threshold });
+
+            Ok(())
+        }
+
+        /// Performs a privileged action authorized by the owner, unless it has to be scheduled
+        fn run_admin_action(&mut self, action: AdminAction) -> Result<(), PSP22Error> {
+            if self.timelock_enabled && action.is_timelocked() {
+                return Err(PSP22Error::Timelocked);
+            }
+            self.apply_admin_action(action)
+        }
+
+        /// Queues `action` to be performed from `eta` on, returning the operation id
+        fn schedule_action(&mut self, action: AdminAction, eta: u64) -> Result<u64, PSP22Error> {
+            let id = self.next_timelock_operation_id;
+            self.next_timelock_operation_id = id.checked_add(1).ok_or(PSP22Error::Overflow)?;
+            self.timelock_operations.insert(
+                id,
+                &TimelockOperation {
+                    action: action.clone(),
+                    eta,
+                },
+            );
+            self.add_pending_operation(id);
+
+            self.env().emit_event(OperationScheduled { id, action, eta });
+
+            Ok(id)
+        }
+
+        /// Appends operation `id` to the pending operation index
+        fn add_pending_operation(&mut self, id: u64) {
+            let index = self.pending_operation_count();
+            self.pending_operation_ids.insert(index, &id);
+            self.pending_operation_indices.insert(id, &index);
+            self.pending_operation_count.set(&(index + 1));
+        }
+
+        /// Drops operation `id` from the pending operation index once it's executed or canceled
+        ///
+        /// The last operation moves into the freed slot, so listing order isn't stable.
+        fn remove_pending_operation(&mut self, id: u64) {
+            let Some(index) = self.pending_operation_indices.take(id) else {
+                return;
+            };
+
+            let last = self.pending_operation_count() - 1;
+            if index != last {
+                if let Some(moved) = self.pending_operation_ids.get(last) {
+                    self.pending_operation_ids.insert(index, &moved);
+                    self.pending_operation_indices.insert(moved, &index);
+                }
+            }
+            self.pending_operation_ids.remove(last);
+            self.pending_operation_count.set(&last);
+        }
+
+        /// Performs a privileged action
+        ///
+        /// Callers are responsible for authorization: privileged messages check
+        /// the owner, `execute` checks the multisig confirmations,
+        /// `execute_scheduled` relies on the check done when scheduling and
+        /// `execute_proposal` on the holder vote.
+        fn apply_admin_action(&mut self, action: AdminAction) -> Result<(), PSP22Error> {
+            self.ensure_migrated_for(&action)?;
+            match action {
+                AdminAction::Mint { to, value } => {
+                    self.ensure_not_wrapped()?;
+                    self.mint_to(to, value)
+                }
+                AdminAction::AddVoucherSigner { signer } => {
+                    self.voucher_signers.insert(signer, &());
+                    self.env().emit_event(VoucherSignerChanged {
+                        signer,
+                        active: true,
+                    });
+                    Ok(())
+                }
+                AdminAction::RemoveVoucherSigner { signer } => {
+                    self.voucher_signers.remove(signer);
+                    self.env().emit_event(VoucherSignerChanged {
+                        signer,
+                        active: false,
+                    });
+                    Ok(())
+                }
+                AdminAction::SetRewardRate { reward_rate } => {
+                    self.ensure_not_wrapped()?;
+
+                    // Emissions up to now are accrued at the previous rate
+                    self.accrue_rewards()?;
+                    self.reward_rate = reward_rate;
+
+                    Ok(())
+                }
+                AdminAction::SetUnbondingPeriod { unbonding_period } => {
+                    self.unbonding_period = unbonding_period;
+                    Ok(())
+                }
+                AdminAction::Upgrade { code_hash } => {
+                    self.env()
+                        .set_code_hash(&code_hash)
+                        .map_err(|_| PSP22Error::UpgradeFailed)?;
+
+                    self.env().emit_event(Upgraded { code_hash });
+
+                    Ok(())
+                }
+                AdminAction::Migrate => {
+                    let from_version = self.storage_version;
+                    if from_version >= STORAGE_VERSION {
+                        return Err(PSP22Error::AlreadyMigrated);
+                    }
+
+                    // Migration steps go here, one per version bump, e.g.
+                    // `if self.storage_version < 2 { ...; self.storage_version = 2; }`
+                    self.storage_version = STORAGE_VERSION;
+
+                    self.env().emit_event(Migrated {
+                        from_version,
+                        to_version: STORAGE_VERSION,
+                    });
+
+                    Ok(())
+                }
+                AdminAction::RecoverPsp22 { token, to, amount } => {
+                    let contract = self.env().address();
+                    if token == contract {
+                        let stray = self.balance_of(contract) - self.custody;
+                        if amount > stray {
+                            return Err(PSP22Error::InsufficientBalance);
+                        }
+                        self.transfer_from_to(contract, to, amount)?;
+                    } else {
+                        let result = build_call::<Environment>()
+                            .call(token)
+                            .exec_input(
+                                ExecutionInput::new(Selector::new(PSP22_TRANSFER_SELECTOR))
+                                    .push_arg(to)
+                                    .push_arg(amount)
+                                    .push_arg(Vec::<u8>::new()),
+                            )
+                            .returns::<Result<(), PSP22Error>>()
+                            .try_invoke();
+
+                        if !matches!(result, Ok(Ok(Ok(())))) {
+                            return Err(PSP22Error::RecoveryFailed);
+                        }
+                    }
+
+                    self.env().emit_event(TokensRecovered { token, to, amount });
+
+                    Ok(())
+                }
+                AdminAction::RecoverNative { to, amount } => {
+                    let stray = self
+                        .env()
+                        .balance()
+                        .saturating_sub(self.reserved_native_balance());
+                    if amount > stray {
+                        return Err(PSP22Error::InsufficientBalance);
+                    }
+
+                    self.env()
+                        .transfer(to, amount)
+                        .map_err(|_| PSP22Error::NativeTransferFailed)?;
+
+                    self.env().emit_event(NativeRecovered { to, amount });
+
+                    Ok(())
+                }
+                AdminAction::TransferOwnership { new_owner } => {
+                    self.pending_owner = Some(new_owner);
+
+                    self.env().emit_event(OwnershipTransferStarted {
+                        previous_owner: self.owner,
+                        new_owner,
+                    });
+
+                    Ok(())
+                }
+                AdminAction::CancelOwnershipTransfer => {
+                    let pending_owner = self
+                        .pending_owner
+                        .take()
+                        .ok_or(PSP22Error::NoPendingOwner)?;
+
+                    self.env().emit_event(OwnershipTransferCanceled {
+                        owner: self.owner,
+                        pending_owner,
+                    });
+
+                    Ok(())
+                }
+                AdminAction::EnableMultisig { signers, threshold } => {
+                    if self.is_multisig_enabled() {
+                        return Err(PSP22Error::MultisigEnabled);
+                    }
+
+                    for signer in signers {
+                        self.add_multisig_signer(signer)?;
+                    }
+                    self.set_multisig_threshold(threshold)?;
+
+                    // The multisig is in control while the contract owns itself
+                    self.owner = self.env().address();
+                    self.pending_owner = None;
+
+                    Ok(())
+                }
+                AdminAction::AddSigner { signer } => self.add_multisig_signer(signer),
+                AdminAction::RemoveSigner { signer } => {
+                    if !self.multisig_signers.contains(signer) {
+                        return Err(PSP22Error::NotSigner);
+                    }
+                    if self.multisig_signer_count <= self.multisig_threshold {
+                        return Err(PSP22Error::InvalidThreshold);
+                    }
+
+                    self.multisig_signers.remove(signer);
+                    self.multisig_signer_count -= 1;
+
+                    self.env().emit_event(MultisigSignerRemoved { signer });
+
+                    Ok(())
+                }
+                AdminAction::SetThreshold { threshold } => self.set_multisig_threshold(threshold),
+                AdminAction::EnableTimelock { min_delay } => {
+                    if self.timelock_enabled {
+                        return Err(PSP22Error::TimelockEnabled);
+                    }
+
+                    self.timelock_enabled = true;
+                    self.timelock_min_delay = min_delay;
+
+                    self.env().emit_event(TimelockDelayChanged { min_delay });
+
+                    Ok(())
+                }
+                AdminAction::SetTimelockDelay { min_delay } => {
+                    self.timelock_min_delay = min_delay;
+
+                    self.env().emit_event(TimelockDelayChanged { min_delay });
+
+                    Ok(())
+                }
+                AdminAction::SetGovernanceSettings {
+                    quorum,
+                    voting_period,
+                } => {
+                    if voting_period == 0 {
+                        return Err(PSP22Error::InvalidVotingPeriod);
+                    }
+                    if quorum.is_zero() {
+                        return Err(PSP22Error::InvalidParameter);
+                    }
+
+                    if self.governance_voting_period == 0 {
+                        self.checkpoint_start_block.set(&self.env().block_number());
+                    }
+                    self.governance_quorum = quorum;
+                    self.governance_voting_period = voting_period;
+
+                    self.env().emit_event(GovernanceSettingsChanged {
+                        quorum,
+                        voting_period,
+                    });
+
+                    Ok(())
+                }
+                AdminAction::SetProposalThreshold { threshold } => {
+                    self.governance_proposal_threshold.set(&threshold);
+
+                    self.env()
+                        .emit_event(ProposalThresholdChanged { threshold });
+
+                    Ok(())
+                }
+                AdminAction::AddRelayer { relayer } => {
+                    self.ensure_not_zero(relayer)?;
+                    if self.bridge_relayers.contains(relayer) {
+                        return Err(PSP22Error::AlreadyRelayer);
+                    }
+
+                    self.bridge_relayers.insert(relayer, &());
+                    self.bridge_relayer_count = self
+                        .bridge_relayer_count
+                        .checked_add(1)
+                        .ok_or(PSP22Error::Overflow)?;
+
+                    self.env().emit_event(RelayerChanged {
+                        relayer,
+                        active: true,
+                    });
+
+                    Ok(())
+                }
+                AdminAction::RemoveRelayer { relayer } => {
+                    if !self.bridge_relayers.contains(relayer) {
+                        return Err(PSP22Error::NotRelayer);
+                    }
+                    if self.bridge_relayer_count <= self.bridge_threshold {
+                        return Err(PSP22Error::InvalidThreshold);
+                    }
+
+                    self.bridge_relayers.remove(relayer);
+                    self.bridge_relayer_count -= 1;
+
+                    self.env().emit_event(RelayerChanged {
+                        relayer,
+                        active: false,
+                    });
+
+                    Ok(())
+                }
+                AdminAction::SetRelayerThreshold { threshold } => {
+                    if threshold == 0 || threshold > self.bridge_relayer_count {
+                        return Err(PSP22Error::InvalidThreshold);
+                    }
+
+                    self.bridge_threshold = threshold;
+
+                    Ok(())
+                }
+                AdminAction::SetBridgeRateLimit {
+                    chain,
+                    limit,
+                    period,
+                } => {
+                    if limit.is_zero() {
+                        self.bridge_rate_limits.remove(chain);
+                    } else {
+                        // A zero-length window would reset on every mint and cap
+                        // single transfers instead of the chain
+                        if period == 0 {
+                            return Err(PSP22Error::InvalidParameter);
+                        }
+
+                        // Keep the current window so lowering a limit takes effect right away
+                        let mut rate_limit = self.bridge_rate_limits.get(chain).unwrap_or_default();
+                        rate_limit.limit = limit;
+                        rate_limit.period = period;
+                        self.bridge_rate_limits.insert(chain, &rate_limit);
+                    }
+
+                    self.env().emit_event(BridgeRateLimitChanged {
+                        chain,
+                        limit,
+                        period,
+                    });
+
+                    Ok(())
+                }
+                AdminAction::CancelScheduled { id } => {
+                    if self.timelock_operations.take(id).is_none() {
+                        return Err(PSP22Error::OperationNotFound);
+                    }
+                    self.remove_pending_operation(id);
+
+                    self.env().emit_event(OperationCanceled { id });
+
+                    Ok(())
+                }
+            }
+        }
+
+        /// Loads a governance proposal or fails with `ProposalNotFound`
+        fn governance_proposal_by_id(&self, id: u64) -> Result<GovernanceProposal, PSP22Error> {
+            self.governance_proposals
+                .get(id)
+                .ok_or(PSP22Error::ProposalNotFound)
+        }
+
+        /// Derives the lifecycle stage of `proposal` from the current block and tally
+        fn state_of(&self, proposal: &GovernanceProposal) -> ProposalState {
+            if proposal.executed {
+                ProposalState::Executed
+            } else if proposal.eta.is_some() {
+                ProposalState::Queued
+            } else if self.env().block_number() <= proposal.vote_end {
+                ProposalState::Active
+            } else if proposal.for_votes >= proposal.quorum
+                && proposal.for_votes > proposal.against_votes
+            {
+                ProposalState::Succeeded
+            } else {
+                ProposalState::Defeated
+            }
+        }
+
+        /// Computes `value * numerator / denominator`, rounding up if `round_up`
+        fn mul_div(
+            value: U256,
+            numerator: U256,
+            denominator: U256,
+            round_up: bool,
+        ) -> Result<U256, PSP22Error> {
+            let product = value.checked_mul(numerator).ok_or(PSP22Error::Overflow)?;
+            let quotient = product / denominator;
+            if round_up && !(product % denominator).is_zero() {
+                return quotient.checked_add(U256::from(1)).ok_or(PSP22Error::Overflow);
+            }
+            Ok(quotient)
+        }
+
+        /// Converts assets to shares at the current price, including the virtual offset
+        fn assets_to_shares(&self, assets: U256, round_up: bool) -> Result<U256, PSP22Error> {
+            Self::mul_div(
+                assets,
+                self.vault_total_shares + U256::from(VAULT_VIRTUAL_SHARES),
+                self.vault_assets + U256::from(1),
+                round_up,
+            )
+        }
+
+        /// Converts shares to assets at the current price, including the virtual offset
+        fn shares_to_assets(&self, shares: U256, round_up: bool) -> Result<U256, PSP22Error> {
+            Self::mul_div(
+                shares,
+                self.vault_assets + U256::from(1),
+                self.vault_total_shares + U256::from(VAULT_VIRTUAL_SHARES),
+                round_up,
+            )
+        }
+
+        /// Stores a share balance, removing the entry when it drops to zero
+        fn set_shares(&mut self, owner: H160, shares: U256) {
+            if shares.is_zero() {
+                self.vault_shares.remove(owner);
+            } else {
+                self.vault_shares.insert(owner, &shares);
+            }
+        }
+
+        /// Moves `value` shares from `from` to `to`
+        fn transfer_shares(&mut self, from: H160, to: H160, value: U256) -> Result<(), PSP22Error> {
+            self.ensure_not_zero(to)?;
+
+            if from == to || value.is_zero() {
+                return Ok(());
+            }
+
+            let from_shares = self.share_balance_of(from);
+            if from_shares < value {
+                return Err(PSP22Error::InsufficientBalance);
+            }
+            let to_shares = self
+                .share_balance_of(to)
+                .checked_add(value)
+                .ok_or(PSP22Error::Overflow)?;
+
+            self.set_shares(from, from_shares - value);
+            self.set_shares(to, to_shares);
+
+            self.env().emit_event(ShareTransfer {
+                from: Some(from),
+                to: Some(to),
+                value,
+            });
+
+            Ok(())
+        }
+
+        /// Uses `value` of the share allowance `owner` granted to `spender`
+        fn spend_share_allowance(
+            &mut self,
+            owner: H160,
+            spender: H160,
+            value: U256,
+        ) -> Result<(), PSP22Error> {
+            if owner == spender {
+                return Ok(());
+            }
+
+            let allowance = self.share_allowance(owner, spender);
+            if allowance < value {
+                return Err(PSP22Error::InsufficientAllowance);
+            }
+
+            // `U256::MAX` is an unlimited allowance, like for MEME itself
+            if allowance != U256::MAX {
+                self.set_share_allowance(owner, spender, allowance - value);
+            }
+
+            Ok(())
+        }
+
+        /// Stores a share allowance and emits `ShareApproval`
+        fn set_share_allowance(&mut self, owner: H160, spender: H160, value: U256) {
+            if value.is_zero() {
+                self.vault_share_allowances.remove((owner, spender));
+            } else {
+                self.vault_share_allowances.insert((owner, spender), &value);
+            }
+
+            self.env().emit_event(ShareApproval {
+                owner,
+                spender,
+                value,
+            });
+        }
+
+        /// Takes `assets` from the caller into the vault and mints `shares` to `receiver`
+        fn vault_enter(
+            &mut self,
+            receiver: H160,
+            assets: U256,
+            shares: U256,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_not_zero(receiver)?;
+            if assets.is_zero() || shares.is_zero() {
+                return Err(PSP22Error::ZeroAmount);
+            }
+
+            let sender = self.caller();
+            self.lock_in_custody(sender, assets)?;
+            self.vault_assets = self
+                .vault_assets
+                .checked_add(assets)
+                .ok_or(PSP22Error::Overflow)?;
+
+            let receiver_shares = self
+                .share_balance_of(receiver)
+                .checked_add(shares)
+                .ok_or(PSP22Error::Overflow)?;
+            self.vault_total_shares = self
+                .vault_total_shares
+                .checked_add(shares)
+                .ok_or(PSP22Error::Overflow)?;
+            self.set_shares(receiver, receiver_shares);
+
+            self.env().emit_event(ShareTransfer {
+                from: None,
+                to: Some(receiver),
+                value: shares,
+            });
+            self.env().emit_event(VaultDeposit {
+                sender,
+                owner: receiver,
+                assets,
+                shares,
+            });
+
+            Ok(())
+        }
+
+        /// Burns `shares` of `owner` and pays `assets` out of the vault to `receiver`
+        ///
+        /// Callers other than `owner` spend their share allowance.
+        fn vault_exit(
+            &mut self,
+            receiver: H160,
+            owner: H160,
+            assets: U256,
+            shares: U256,
+        ) -> Result<(), PSP22Error> {
+            if assets.is_zero() || shares.is_zero() {
+                return Err(PSP22Error::ZeroAmount);
+            }
+
+            let sender = self.caller();
+            let owner_shares = self.share_balance_of(owner);
+            if owner_shares < shares {
+                return Err(PSP22Error::InsufficientBalance);
+            }
+            self.spend_share_allowance(owner, sender, shares)?;
+
+            self.set_shares(owner, owner_shares - shares);
+            self.vault_total_shares = self.vault_total_shares - shares;
+            self.vault_assets = self
+                .vault_assets
+                .checked_sub(assets)
+                .ok_or(PSP22Error::InsufficientBalance)?;
+            self.release_from_custody(receiver, assets)?;
+
+            self.env().emit_event(ShareTransfer {
+                from: Some(owner),
+                to: None,
+                value: shares,
+            });
+            self.env().emit_event(VaultWithdraw {
+                sender,
+                receiver,
+                owner,
+                assets,
+                shares,
+            });
+
+            Ok(())
+        }
+    }
+
+    impl PspCoin {
+        // PSP22 Standard Functions
+
+        /// Returns the total token supply
+        #[ink(message)]
+        pub fn total_supply(&self) -> U256 {
+            self.total_supply
+        }
+
+        /// Returns the balance of an account
+        #[ink(message)]
+        pub fn balance_of(&self, owner: H160) -> U256 {
+            self.account(owner).balance
+        }
+
+        /// Returns the allowance of a spender for an owner
+        ///
+        /// Expired allowances are reported as 0.
+        #[ink(message)]
+        pub fn allowance(&self, owner: H160, spender: H160) -> U256 {
+            let allowance = self.allowance_entry(owner, spender);
+            if allowance.is_expired(self.env().block_timestamp()) {
+                return U256::from(0);
+            }
+            allowance.value
+        }
+
+        /// Transfers tokens from the caller to another account
+        #[ink(message)]
+        pub fn transfer(
+            &mut self,
+            to: H160,
+            value: U256,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            let from = self.caller();
+            self.transfer_from_to(from, to, value)
+        }
+
+        /// Transfers tokens from one account to another using allowance
+        ///
+        /// An allowance of `U256::MAX` is treated as unlimited: it is left
+        /// untouched and no `Approval` event is emitted.
+        #[ink(message)]
+        pub fn transfer_from(
+            &mut self,
+            from: H160,
+            to: H160,
+            value: U256,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            let caller = self.caller();
+
+            // No-op if from and to are the same or value is zero
+            if from == to || value.is_zero() {
+                return Ok(());
+            }
+
+            // If caller is not the owner, check allowance
+            if caller != from {
+                let mut allowance = self.allowance_entry(from, caller);
+
+                if allowance.is_expired(self.env().block_timestamp()) {
+                    return Err(PSP22Error::AllowanceExpired);
+                }
+
+                if allowance.value < value {
+                    return Err(PSP22Error::InsufficientAllowance);
+                }
+
+                // `U256::MAX` is an unlimited allowance, it is never decreased
+                if allowance.value != U256::MAX {
+                    // Decrease allowance
+                    allowance.value = allowance.value - value;
+                    self.set_allowance(from, caller, &allowance);
+
+                    self.env().emit_event(Approval {
+                        owner: from,
+                        spender: caller,
+                        value: allowance.value,
+                    });
+                }
+            }
+
+            self.transfer_from_to(from, to, value)
+        }
+
+        /// Approves a spender to spend tokens on behalf of the caller
+        #[ink(message)]
+        pub fn approve(&mut self, spender: H160, value: U256) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            self.ensure_not_zero(spender)?;
+            let owner = self.caller();
+
+            // No-op if owner and spender are the same
+            if owner == spender {
+                return Ok(());
+            }
+
+            self.approve_until(owner, spender, value, None);
+
+            Ok(())
+        }
+
+        /// Approves a spender to spend tokens on behalf of the caller until `expires_at`
+        ///
+        /// Once the block timestamp reaches `expires_at` the allowance reads as 0
+        /// and `transfer_from` fails with `AllowanceExpired`.
+        #[ink(message)]
+        pub fn approve_with_expiry(
+            &mut self,
+            spender: H160,
+            value: U256,
+            expires_at: u64,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            self.ensure_not_zero(spender)?;
+            let owner = self.caller();
+
+            // No-op if owner and spender are the same
+            if owner == spender {
+                return Ok(());
+            }
+
+            if expires_at <= self.env().block_timestamp() {
+                return Err(PSP22Error::AllowanceExpired);
+            }
+
+            self.approve_until(owner, spender, value, Some(expires_at));
+
+            Ok(())
+        }
+
+        /// Returns when the allowance of a spender for an owner expires, if ever
+        #[ink(message)]
+        pub fn allowance_expiry(&self, owner: H160, spender: H160) -> Option<u64> {
+            self.allowances
+                .get((owner, spender))
+                .and_then(|allowance| allowance.expires_at)
+        }
+
+        /// Increases the allowance of a spender
+        #[ink(message)]
+        pub fn increase_allowance(
+            &mut self,
+            spender: H160,
+            delta_value: U256,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            self.ensure_not_zero(spender)?;
+            let owner = self.caller();
+
+            // No-op if owner and spender are the same or delta_value is zero
+            if owner == spender || delta_value.is_zero() {
+                return Ok(());
+            }
+
+            let mut allowance = self.allowance_entry(owner, spender);
+
+            // An expired allowance starts over from zero without expiry
+            if allowance.is_expired(self.env().block_timestamp()) {
+                allowance = Allowance::default();
+            }
+
+            allowance.value = allowance
+                .value
+                .checked_add(delta_value)
+                .ok_or(PSP22Error::Overflow)?;
+
+            self.set_allowance(owner, spender, &allowance);
+
+            self.env().emit_event(Approval {
+                owner,
+                spender,
+                value: allowance.value,
+            });
+
+            Ok(())
+        }
+
+        /// Decreases the allowance of a spender
+        #[ink(message)]
+        pub fn decrease_allowance(
+            &mut self,
+            spender: H160,
+            delta_value: U256,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            let owner = self.caller();
+
+            // No-op if owner and spender are the same or delta_value is zero
+            if owner == spender || delta_value.is_zero() {
+                return Ok(());
+            }
+
+            let mut allowance = self.allowance_entry(owner, spender);
+
+            if self.allowance(owner, spender) < delta_value {
+                return Err(PSP22Error::InsufficientAllowance);
+            }
+
+            allowance.value = allowance.value - delta_value;
+            self.set_allowance(owner, spender, &allowance);
+
+            self.env().emit_event(Approval {
+                owner,
+                spender,
+                value: allowance.value,
+            });
+
+            Ok(())
+        }
+
+        // PSP22 Callable Functions (ERC-1363)
+
+        /// Transfers tokens to `to` and then calls its `on_transfer_received` hook
+        ///
+        /// The hook receives `(operator, from, value, data)` and must return
+        /// `ON_TRANSFER_RECEIVED`, otherwise the whole call reverts.
+        #[ink(message)]
+        pub fn transfer_and_call(
+            &mut self,
+            to: H160,
+            value: U256,
+            data: Vec<u8>,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            let from = self.caller();
+            self.transfer_from_to(from, to, value)?;
+            self.call_receiver(to, ON_TRANSFER_RECEIVED, (from, from, value, data))
+        }
+
+        /// Approves `spender` and then calls its `on_approval_received` hook
+        ///
+        /// The hook receives `(owner, value, data)` and must return
+        /// `ON_APPROVAL_RECEIVED`, otherwise the whole call reverts.
+        #[ink(message)]
+        pub fn approve_and_call(
+            &mut self,
+            spender: H160,
+            value: U256,
+            data: Vec<u8>,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            let owner = self.caller();
+            self.approve(spender, value)?;
+            self.call_receiver(spender, ON_APPROVAL_RECEIVED, (owner, value, data))
+        }
+
+        // PSP22 Metadata Functions
+
+        /// Returns the token name
+        #[ink(message)]
+        pub fn name(&self) -> Option<String> {
+            self.metadata.get().map(|metadata| metadata.name)
+        }
+
+        /// Returns the token symbol
+        #[ink(message)]
+        pub fn symbol(&self) -> Option<String> {
+            self.metadata.get().map(|metadata| metadata.symbol)
+        }
+
+        /// Returns the token decimals
+        #[ink(message)]
+        pub fn decimals(&self) -> u8 {
+            self.metadata
+                .get()
+                .map(|metadata| metadata.decimals)
+                .unwrap_or_default()
+        }
+
+        // PSP22 Mintable Functions
+
+        /// Mints new tokens to the caller's account (owner only)
+        #[ink(message)]
+        pub fn mint(&mut self, value: U256) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            let caller = self.caller();
+            self.run_admin_action(AdminAction::Mint { to: caller, value })
+        }
+
+        // PSP22 Burnable Functions
+
+        /// Burns tokens from the caller's account
+        #[ink(message)]
+        pub fn burn(&mut self, value: U256) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            self.ensure_not_wrapped()?;
+            let caller = self.caller();
+            self.burn_from(caller, value)
+        }
+
+        // Wrapped Native Currency Functions
+
+        /// Returns whether the token wraps the chain's native currency
+        #[ink(message)]
+        pub fn is_wrapped(&self) -> bool {
+            self.wrapped
+        }
+
+        /// Wraps the transferred native currency into the same amount of tokens
+        #[ink(message, payable)]
+        pub fn deposit(&mut self) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            self.ensure_wrapped()?;
+
+            let caller = self.caller();
+            let value = self.env().transferred_value();
+            if value.is_zero() {
+                return Err(PSP22Error::ZeroAmount);
+            }
+            self.mint_to(caller, value)?;
+
+            self.env().emit_event(Deposit { dst: caller, value });
+
+            Ok(())
+        }
+
+        /// Burns `value` tokens and sends the same amount of native currency back to the caller
+        #[ink(message)]
+        pub fn withdraw(&mut self, value: U256) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            self.ensure_wrapped()?;
+            if value.is_zero() {
+                return Err(PSP22Error::ZeroAmount);
+            }
+
+            let caller = self.caller();
+            self.burn_from(caller, value)?;
+
+            self.env()
+                .transfer(caller, value)
+                .map_err(|_| PSP22Error::NativeTransferFailed)?;
+
+            self.env().emit_event(Withdrawal { src: caller, value });
+
+            Ok(())
+        }
+
+        // Ownership Functions
+
+        /// Returns the contract owner
+        #[ink(message)]
+        pub fn owner(&self) -> H160 {
+            self.owner
+        }
+
+        /// Returns the account nominated as the next owner, if any
+        #[ink(message)]
+        pub fn pending_owner(&self) -> Option<H160> {
+            self.pending_owner
+        }
+
+        /// Nominates `new_owner` as the next owner (owner only)
+        ///
+        /// Ownership only changes once `new_owner` calls `accept_ownership`, so
+        /// control can't be handed to a mistyped address. A new nomination
+        /// replaces the pending one.
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self, new_owner: H160) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            self.run_admin_action(AdminAction::TransferOwnership { new_owner })
+        }
+
+        /// Makes the caller the owner (pending owner only)
+        #[ink(message)]
+        pub fn accept_ownership(&mut self) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            let caller = self.caller();
+            if self.pending_owner != Some(caller) {
+                return Err(PSP22Error::Unauthorized);
+            }
+
+            let previous_owner = self.owner;
+            self.owner = caller;
+            self.pending_owner = None;
+
+            self.env().emit_event(OwnershipTransferred {
+                previous_owner,
+                new_owner: caller,
+            });
+
+            Ok(())
+        }
+
+        /// Withdraws the pending nomination (owner only)
+        #[ink(message)]
+        pub fn cancel_ownership_transfer(&mut self) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            self.run_admin_action(AdminAction::CancelOwnershipTransfer)
+        }
+
+        // Upgrade Functions
+
+        /// Returns the layout version of the data currently in storage
+        #[ink(message)]
+        pub fn storage_version(&self) -> u32 {
+            self.storage_version
+        }
+
+        /// Replaces the contract code with the code stored under `code_hash` (owner only)
+        ///
+        /// Storage is kept as is. The new code must keep the packed root fields
+        /// of `PspCoin` unchanged; if it changes what `Lazy` or `Mapping` fields
+        /// hold, the owner must call `migrate` on it right after the upgrade.
+        /// Until then every other state-changing message fails with
+        /// `MigrationRequired`, except upgrading again and canceling scheduled
+        /// operations.
+        #[ink(message)]
+        pub fn upgrade(&mut self, code_hash: H256) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            self.run_admin_action(AdminAction::Upgrade { code_hash })
+        }
+
+        /// Brings storage written by older code up to `STORAGE_VERSION` (owner only)
+        ///
+        /// Fails if storage is already at the current version, so each
+        /// migration runs exactly once. This message decodes the root storage
+        /// cell like any other, so migrations can only rewrite `Lazy` and
+        /// `Mapping` contents. It isn't timelocked, as the upgrade it completes
+        /// already was.
+        #[ink(message)]
+        pub fn migrate(&mut self) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            self.run_admin_action(AdminAction::Migrate)
+        }
+
+        // Mint Voucher Functions
+
+        /// Returns whether `signer` may sign mint vouchers
+        #[ink(message)]
+        pub fn is_voucher_signer(&self, signer: H160) -> bool {
+            self.voucher_signers.contains(signer)
+        }
+
+        /// Returns whether a voucher nonce has already been redeemed
+        #[ink(message)]
+        pub fn is_voucher_nonce_used(&self, nonce: u128) -> bool {
+            self.used_voucher_nonces.contains(nonce)
+        }
+
+        /// Authorizes `signer` to sign mint vouchers (owner only)
+        #[ink(message)]
+        pub fn add_voucher_signer(&mut self, signer: H160) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            self.run_admin_action(AdminAction::AddVoucherSigner { signer })
+        }
+
+        /// Revokes the voucher signing rights of `signer` (owner only)
+        #[ink(message)]
+        pub fn remove_voucher_signer(&mut self, signer: H160) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            self.run_admin_action(AdminAction::RemoveVoucherSigner { signer })
+        }
+
+        /// Mints `amount` tokens to `to` against a voucher signed by an authorized signer
+        ///
+        /// `signature` is a 65-byte recoverable ECDSA signature over
+        /// `keccak256(scale_encode(keccak256(MINT_VOUCHER_TYPE), contract, chain_id, to,
+        /// amount, nonce, expiry))`. Each nonce can be redeemed only once. Like
+        /// allowances, the voucher is valid while the block timestamp is before
+        /// `expiry` and expires at `expiry` itself.
+        #[ink(message)]
+        pub fn redeem_voucher(
+            &mut self,
+            to: H160,
+            amount: U256,
+            nonce: u128,
+            expiry: u64,
+            signature: [u8; 65],
+        ) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            self.ensure_not_wrapped()?;
+            self.ensure_chain_id()?;
+
+            if self.env().block_timestamp() >= expiry {
+                return Err(PSP22Error::VoucherExpired);
+            }
+
+            if self.used_voucher_nonces.contains(nonce) {
+                return Err(PSP22Error::VoucherAlreadyRedeemed);
+            }
+
+            let message_hash = self.voucher_hash(to, amount, nonce, expiry);
+            let signer = self.recover_signer(&message_hash, &signature)?;
+
+            if !self.voucher_signers.contains(signer) {
+                return Err(PSP22Error::InvalidSignature);
+            }
+
+            self.used_voucher_nonces.insert(nonce, &());
+            self.mint_to(to, amount)?;
+
+            self.env().emit_event(VoucherRedeemed {
+                to,
+                signer,
+                amount,
+                nonce,
+            });
+
+            Ok(())
+        }
+
+        // Escrow Functions
+
+        /// Returns the escrow stored under `id`
+        #[ink(message)]
+        pub fn escrow(&self, id: u64) -> Option<Escrow> {
+            self.escrows.get(id)
+        }
+
+        /// Returns the amount of tokens the contract holds in custody
+        #[ink(message)]
+        pub fn custody_balance(&self) -> U256 {
+            self.custody
+        }
+
+        /// Locks `amount` of the caller's tokens until they are released to `payee`
+        /// or refunded, and returns the escrow id
+        ///
+        /// The caller or `arbiter` can release the tokens to `payee`. `arbiter` can
+        /// refund them at any time, anyone can refund them once `deadline` has passed.
+        #[ink(message)]
+        pub fn open_escrow(
+            &mut self,
+            payee: H160,
+            arbiter: H160,
+            amount: U256,
+            deadline: u64,
+        ) -> Result<u64, PSP22Error> {
+            self.ensure_migrated()?;
+            self.ensure_not_zero(payee)?;
+
+            if amount.is_zero() {
+                return Err(PSP22Error::ZeroAmount);
+            }
+
+            if deadline <= self.env().block_timestamp() {
+                return Err(PSP22Error::InvalidDeadline);
+            }
+
+            let payer = self.caller();
+            self.lock_in_custody(payer, amount)?;
+
+            let id = self.next_escrow_id;
+            self.next_escrow_id = id.checked_add(1).ok_or(PSP22Error::Overflow)?;
+            self.escrows.insert(
+                id,
+                &Escrow {
+                    payer,
+                    payee,
+                    arbiter,
+                    amount,
+                    deadline,
+                    status: EscrowStatus::Open,
+                },
+            );
+
+            self.env().emit_event(EscrowOpened {
+                id,
+                payer,
+                payee,
+                arbiter,
+                amount,
+                deadline,
+            });
+
+            Ok(id)
+        }
+
+        /// Pays the escrowed tokens out to the payee (payer or arbiter only)
+        #[ink(message)]
+        pub fn release(&mut self, id: u64) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            let mut escrow = self.open_escrow_by_id(id)?;
+
+            let caller = self.caller();
+            if caller != escrow.payer && caller != escrow.arbiter {
+                return Err(PSP22Error::Unauthorized);
+            }
+
+            escrow.status = EscrowStatus::Released;
+            self.escrows.insert(id, &escrow);
+            self.release_from_custody(escrow.payee, escrow.amount)?;
+
+            self.env().emit_event(EscrowReleased {
+                id,
+                payee: escrow.payee,
+                amount: escrow.amount,
+            });
+
+            Ok(())
+        }
+
+        /// Returns the escrowed tokens to the payer
+        /// (arbiter at any time, anyone once the deadline has passed)
+        #[ink(message)]
+        pub fn refund(&mut self, id: u64) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            let mut escrow = self.open_escrow_by_id(id)?;
+
+            let caller = self.caller();
+            if caller != escrow.arbiter && self.env().block_timestamp() < escrow.deadline {
+                return Err(PSP22Error::EscrowNotExpired);
+            }
+
+            escrow.status = EscrowStatus::Refunded;
+            self.escrows.insert(id, &escrow);
+            self.release_from_custody(escrow.payer, escrow.amount)?;
+
+            self.env().emit_event(EscrowRefunded {
+                id,
+                payer: escrow.payer,
+                amount: escrow.amount,
+            });
+
+            Ok(())
+        }
+
+        // Streaming Functions
+
+        /// Returns the stream stored under `id`
+        #[ink(message)]
+        pub fn stream(&self, id: u64) -> Option<Stream> {
+            self.streams.get(id)
+        }
+
+        /// Locks `deposit` of the caller's tokens and streams them to `recipient`
+        /// linearly between the `start` and `stop` timestamps, returning the stream id
+        #[ink(message)]
+        pub fn create_stream(
+            &mut self,
+            recipient: H160,
+            deposit: U256,
+            start: u64,
+            stop: u64,
+        ) -> Result<u64, PSP22Error> {
+            self.ensure_migrated()?;
+            if deposit.is_zero() {
+                return Err(PSP22Error::ZeroAmount);
+            }
+
+            if start >= stop || stop <= self.env().block_timestamp() {
+                return Err(PSP22Error::InvalidSchedule);
+            }
+
+            self.ensure_not_zero(recipient)?;
+            let sender = self.caller();
+            if sender == recipient {
+                return Err(PSP22Error::InvalidRecipient);
+            }
+
+            self.lock_in_custody(sender, deposit)?;
+
+            let id = self.next_stream_id;
+            self.next_stream_id = id.checked_add(1).ok_or(PSP22Error::Overflow)?;
+            self.streams.insert(
+                id,
+                &Stream {
+                    sender,
+                    recipient,
+                    deposit,
+                    start,
+                    stop,
+                    withdrawn: U256::from(0),
+                },
+            );
+
+            self.env().emit_event(StreamCreated {
+                id,
+                sender,
+                recipient,
+                deposit,
+                start,
+                stop,
+            });
+
+            Ok(id)
+        }
+
+        /// Returns the part of stream `id` that belongs to `who` right now
+        ///
+        /// For the recipient this is what has been streamed but not withdrawn yet,
+        /// for the sender what has not been streamed yet.
+        #[ink(message)]
+        pub fn balance_of_stream(&self, id: u64, who: H160) -> U256 {
+            let Some(stream) = self.streams.get(id) else {
+                return U256::from(0);
+            };
+
+            let streamed = stream.streamed_at(self.env().block_timestamp());
+            if who == stream.recipient {
+                streamed - stream.withdrawn
+            } else if who == stream.sender {
+                stream.deposit - streamed
+            } else {
+                U256::from(0)
+            }
+        }
+
+        /// Withdraws `amount` of the already streamed tokens (recipient only)
+        #[ink(message)]
+        pub fn withdraw_from_stream(&mut self, id: u64, amount: U256) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            let mut stream = self.stream_by_id(id)?;
+
+            let caller = self.caller();
+            if caller != stream.recipient {
+                return Err(PSP22Error::Unauthorized);
+            }
+
+            if amount > self.balance_of_stream(id, caller) {
+                return Err(PSP22Error::InsufficientBalance);
+            }
+
+            stream.withdrawn = stream.withdrawn + amount;
+            if stream.withdrawn == stream.deposit {
+                self.streams.remove(id);
+            } else {
+                self.streams.insert(id, &stream);
+            }
+            self.release_from_custody(caller, amount)?;
+
+            self.env().emit_event(WithdrawFromStream {
+                id,
+                recipient: caller,
+                amount,
+            });
+
+            Ok(())
+        }
+
+        /// Stops stream `id`, paying the recipient what has been streamed so far
+        /// and returning the rest to the sender (sender or recipient only)
+        #[ink(message)]
+        pub fn cancel_stream(&mut self, id: u64) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            let stream = self.stream_by_id(id)?;
+
+            let caller = self.caller();
+            if caller != stream.sender && caller != stream.recipient {
+                return Err(PSP22Error::Unauthorized);
+            }
+
+            let recipient_amount = self.balance_of_stream(id, stream.recipient);
+            let sender_amount = self.balance_of_stream(id, stream.sender);
+
+            self.streams.remove(id);
+            self.release_from_custody(stream.recipient, recipient_amount)?;
+            self.release_from_custody(stream.sender, sender_amount)?;
+
+            self.env().emit_event(StreamCanceled {
+                id,
+                sender_amount,
+                recipient_amount,
+            });
+
+            Ok(())
+        }
+
+        // Staking Functions
+
+        /// Returns the amount of tokens `staker` has staked
+        #[ink(message)]
+        pub fn staked_balance_of(&self, staker: H160) -> U256 {
+            self.stakes.get(staker).unwrap_or_default().staked
+        }
+
+        /// Returns the rewards `staker` could claim right now
+        #[ink(message)]
+        pub fn pending_rewards(&self, staker: H160) -> U256 {
+            let stake = self.stakes.get(staker).unwrap_or_default();
+            let earned = stake
+                .staked
+                .saturating_mul(self.current_reward_per_token() - stake.reward_per_token_paid)
+                / U256::from(REWARD_PRECISION);
+            stake.rewards.saturating_add(earned)
+        }
+
+        /// Returns the tokens `staker` is unbonding and when they can be withdrawn
+        #[ink(message)]
+        pub fn unbonding_of(&self, staker: H160) -> (U256, u64) {
+            let stake = self.stakes.get(staker).unwrap_or_default();
+            (stake.unbonding, stake.unbonding_release)
+        }
+
+        /// Returns the total amount of staked tokens
+        #[ink(message)]
+        pub fn total_staked(&self) -> U256 {
+            self.total_staked
+        }
+
+        /// Returns the amount of tokens minted as staking rewards per millisecond
+        #[ink(message)]
+        pub fn reward_rate(&self) -> U256 {
+            self.reward_rate
+        }
+
+        /// Returns how long unstaked tokens stay locked, in milliseconds
+        #[ink(message)]
+        pub fn unbonding_period(&self) -> u64 {
+            self.unbonding_period
+        }
+
+        /// Sets the amount of tokens minted as staking rewards per millisecond (owner only)
+        #[ink(message)]
+        pub fn set_reward_rate(&mut self, reward_rate: U256) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            self.run_admin_action(AdminAction::SetRewardRate { reward_rate })
+        }
+
+        /// Sets how long unstaked tokens stay locked, in milliseconds (owner only)
+        #[ink(message)]
+        pub fn set_unbonding_period(&mut self, unbonding_period: u64) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            self.run_admin_action(AdminAction::SetUnbondingPeriod { unbonding_period })
+        }
+
+        /// Moves `amount` of the caller's tokens into the staking pool
+        #[ink(message)]
+        pub fn stake(&mut self, amount: U256) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            if amount.is_zero() {
+                return Err(PSP22Error::ZeroAmount);
+            }
+
+            let staker = self.caller();
+            let mut stake = self.update_stake(staker)?;
+
+            self.lock_in_custody(staker, amount)?;
+            stake.staked = stake.staked.checked_add(amount).ok_or(PSP22Error::Overflow)?;
+            self.total_staked = self
+                .total_staked
+                .checked_add(amount)
+                .ok_or(PSP22Error::Overflow)?;
+            self.set_stake(staker, &stake);
+
+            self.env().emit_event(Staked { staker, amount });
+
+            Ok(())
+        }
+
+        /// Takes `amount` of the caller's tokens out of the staking pool
+        ///
+        /// Without an unbonding period the tokens are paid out right away,
+        /// otherwise they can be withdrawn with `withdraw_unbonded` once the
+        /// period has passed. Unstaking again restarts the period for all
+        /// unbonding tokens.
+        #[ink(message)]
+        pub fn unstake(&mut self, amount: U256) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            if amount.is_zero() {
+                return Err(PSP22Error::ZeroAmount);
+            }
+
+            let staker = self.caller();
+            let mut stake = self.update_stake(staker)?;
+
+            if stake.staked < amount {
+                return Err(PSP22Error::InsufficientBalance);
+            }
+
+            stake.staked = stake.staked - amount;
+            self.total_staked = self.total_staked - amount;
+
+            let now = self.env().block_timestamp();
+            let release_at = now.saturating_add(self.unbonding_period);
+            if self.unbonding_period == 0 {
+                self.release_from_custody(staker, amount)?;
+            } else {
+                stake.unbonding = stake
+                    .unbonding
+                    .checked_add(amount)
+                    .ok_or(PSP22Error::Overflow)?;
+                stake.unbonding_release = release_at;
+            }
+            self.set_stake(staker, &stake);
+
+            self.env().emit_event(Unstaked {
+                staker,
+                amount,
+                release_at,
+            });
+
+            Ok(())
+        }
+
+        /// Pays out the caller's unbonded tokens once the unbonding period has passed
+        #[ink(message)]
+        pub fn withdraw_unbonded(&mut self) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            let staker = self.caller();
+            let mut stake = self.stakes.get(staker).unwrap_or_default();
+
+            if stake.unbonding.is_zero() {
+                return Ok(());
+            }
+
+            if self.env().block_timestamp() < stake.unbonding_release {
+                return Err(PSP22Error::StillUnbonding);
+            }
+
+            let amount = stake.unbonding;
+            stake.unbonding = U256::from(0);
+            stake.unbonding_release = 0;
+            self.set_stake(staker, &stake);
+            self.release_from_custody(staker, amount)?;
+
+            self.env().emit_event(UnbondedWithdrawn { staker, amount });
+
+            Ok(())
+        }
+
+        /// Pays out the caller's staking rewards
+        #[ink(message)]
+        pub fn claim_rewards(&mut self) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            let staker = self.caller();
+            let mut stake = self.update_stake(staker)?;
+
+            let amount = stake.rewards;
+            stake.rewards = U256::from(0);
+            self.set_stake(staker, &stake);
+
+            if amount.is_zero() {
+                return Ok(());
+            }
+
+            self.release_from_custody(staker, amount)?;
+
+            self.env().emit_event(RewardsClaimed { staker, amount });
+
+            Ok(())
+        }
+
+        /// Adds `amount` of the caller's tokens to the rewards of the current stakers
+        #[ink(message)]
+        pub fn deposit_rewards(&mut self, amount: U256) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            if amount.is_zero() {
+                return Err(PSP22Error::ZeroAmount);
+            }
+
+            if self.total_staked.is_zero() {
+                return Err(PSP22Error::NothingStaked);
+            }
+
+            self.accrue_rewards()?;
+
+            let from = self.caller();
+            self.lock_in_custody(from, amount)?;
+            self.add_to_reward_per_token(amount)?;
+
+            self.env().emit_event(RewardsDeposited { from, amount });
+
+            Ok(())
+        }
+
+        // Dividend Functions
+
+        /// Returns the native currency `owner` can withdraw as dividends
+        #[ink(message)]
+        pub fn withdrawable_dividend_of(&self, owner: H160) -> U256 {
+            let account = self.account(owner);
+            let earned = self
+                .unsettled_dividends(owner, &account)
+                .unwrap_or_default();
+            account.dividend_credit.saturating_add(earned)
+        }
+
+        /// Returns the native currency distributed to holders but not withdrawn yet
+        #[ink(message)]
+        pub fn dividend_reserve(&self) -> U256 {
+            self.dividend_reserve
+        }
+
+        /// Distributes the transferred native currency to all holders pro rata
+        /// to their balance
+        ///
+        /// Tokens held by the contract itself don't take part, including custody
+        /// held for someone else: staked and unbonding tokens, vault deposits,
+        /// escrows and streams earn their owners nothing while locked. Holders
+        /// collect their share with `withdraw_dividend`.
+        #[ink(message, payable)]
+        pub fn distribute(&mut self) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            let value = self.env().transferred_value();
+            if value.is_zero() {
+                return Err(PSP22Error::ZeroAmount);
+            }
+
+            let contract_balance = self.account(self.env().address()).balance;
+            let eligible_supply = self.total_supply - contract_balance;
+            if eligible_supply.is_zero() {
+                return Err(PSP22Error::NoHolders);
+            }
+
+            let increase = value
+                .checked_mul(U256::from(1) << DIVIDEND_MAGNITUDE_BITS)
+                .ok_or(PSP22Error::Overflow)?
+                / eligible_supply;
+            self.dividend_per_share = self
+                .dividend_per_share
+                .checked_add(increase)
+                .ok_or(PSP22Error::Overflow)?;
+            self.dividend_reserve = self
+                .dividend_reserve
+                .checked_add(value)
+                .ok_or(PSP22Error::Overflow)?;
+
+            self.env().emit_event(DividendsDistributed {
+                from: self.caller(),
+                value,
+            });
+
+            Ok(())
+        }
+
+        /// Sends the caller's dividends to the caller
+        #[ink(message)]
+        pub fn withdraw_dividend(&mut self) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            let caller = self.caller();
+            let mut account = self.settled_account(caller)?;
+
+            let value = account.dividend_credit;
+            if value.is_zero() {
+                return Ok(());
+            }
+
+            account.dividend_credit = U256::from(0);
+            self.set_account(caller, &account);
+            self.dividend_reserve = self.dividend_reserve - value;
+
+            self.env()
+                .transfer(caller, value)
+                .map_err(|_| PSP22Error::NativeTransferFailed)?;
+
+            self.env().emit_event(DividendWithdrawn { to: caller, value });
+
+            Ok(())
+        }
+
+        // Recovery Functions
+
+        /// Returns the native currency the contract holds on behalf of holders
+        ///
+        /// This is the wrapped supply in wrapped mode plus undistributed dividends.
+        #[ink(message)]
+        pub fn reserved_native_balance(&self) -> U256 {
+            let wrapped_supply = if self.wrapped {
+                self.total_supply
+            } else {
+                U256::from(0)
+            };
+            wrapped_supply.saturating_add(self.dividend_reserve)
+        }
+
+        /// Sends `amount` of a PSP22 `token` the contract holds by mistake to `to` (owner only)
+        ///
+        /// For this token itself only the balance above the tokens held in
+        /// custody for escrows, streams and stakes can be recovered.
+        #[ink(message)]
+        pub fn recover_psp22(
+            &mut self,
+            token: H160,
+            to: H160,
+            amount: U256,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            self.run_admin_action(AdminAction::RecoverPsp22 { token, to, amount })
+        }
+
+        /// Sends `amount` of native currency the contract holds by mistake to `to` (owner only)
+        ///
+        /// Native currency backing wrapped tokens or owed as dividends can't be recovered.
+        #[ink(message)]
+        pub fn recover_native(&mut self, to: H160, amount: U256) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            self.run_admin_action(AdminAction::RecoverNative { to, amount })
+        }
+
+        // Multisig Functions
+
+        /// Returns whether privileged actions are controlled by the multisig
+        #[ink(message)]
+        pub fn is_multisig_enabled(&self) -> bool {
+            self.owner == self.env().address()
+        }
+
+        /// Returns whether `account` is a multisig signer
+        #[ink(message)]
+        pub fn is_multisig_signer(&self, account: H160) -> bool {
+            self.multisig_signers.contains(account)
+        }
+
+        /// Returns the number of signers and how many of them must confirm a proposal
+        #[ink(message)]
+        pub fn multisig_threshold(&self) -> (u32, u32) {
+            (self.multisig_threshold, self.multisig_signer_count)
+        }
+
+        /// Returns the pending multisig proposal stored under `id`
+        #[ink(message)]
+        pub fn multisig_proposal(&self, id: u64) -> Option<MultisigProposal> {
+            self.multisig_proposals.get(id)
+        }
+
+        /// Hands control of all privileged actions to an M-of-N signer set (owner only)
+        ///
+        /// The contract becomes its own owner, so privileged messages can't be
+        /// called directly anymore and go through `propose`/`confirm`/`execute`.
+        /// Signer and threshold changes require multisig approval as well.
+        #[ink(message)]
+        pub fn enable_multisig(
+            &mut self,
+            signers: Vec<H160>,
+            threshold: u32,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            self.run_admin_action(AdminAction::EnableMultisig { signers, threshold })
+        }
+
+        /// Proposes a privileged action and confirms it, returning the proposal id (signers only)
+        #[ink(message)]
+        pub fn propose(&mut self, action: AdminAction) -> Result<u64, PSP22Error> {
+            let proposer = self.ensure_multisig_signer()?;
+            self.ensure_migrated_for(&action)?;
+
+            let id = self.next_multisig_proposal_id;
+            self.next_multisig_proposal_id = id.checked_add(1).ok_or(PSP22Error::Overflow)?;
+            self.multisig_proposals.insert(
+                id,
+                &MultisigProposal {
+                    action: action.clone(),
+                    confirmations: ink::prelude::vec![proposer],
+                },
+            );
+
+            self.env().emit_event(MultisigProposed {
+                id,
+                proposer,
+                action,
+            });
+            self.env().emit_event(MultisigConfirmed {
+                id,
+                signer: proposer,
+            });
+
+            Ok(id)
+        }
+
+        /// Confirms proposal `id` (signers only)
+        #[ink(message)]
+        pub fn confirm(&mut self, id: u64) -> Result<(), PSP22Error> {
+            let signer = self.ensure_multisig_signer()?;
+            let mut proposal = self.multisig_proposal_by_id(id)?;
+            self.ensure_migrated_for(&proposal.action)?;
+
+            if proposal.confirmations.contains(&signer) {
+                return Err(PSP22Error::AlreadyConfirmed);
+            }
+
+            proposal.confirmations.push(signer);
+            self.multisig_proposals.insert(id, &proposal);
+
+            self.env().emit_event(MultisigConfirmed { id, signer });
+
+            Ok(())
+        }
+
+        /// Withdraws the caller's confirmation of proposal `id` (signers only)
+        #[ink(message)]
+        pub fn revoke_confirmation(&mut self, id: u64) -> Result<(), PSP22Error> {
+            let signer = self.ensure_multisig_signer()?;
+            let mut proposal = self.multisig_proposal_by_id(id)?;
+            self.ensure_migrated_for(&proposal.action)?;
+
+            let Some(position) = proposal.confirmations.iter().position(|s| *s == signer) else {
+                return Err(PSP22Error::NotConfirmed);
+            };
+
+            proposal.confirmations.swap_remove(position);
+            self.multisig_proposals.insert(id, &proposal);
+
+            self.env().emit_event(MultisigConfirmationRevoked { id, signer });
+
+            Ok(())
+        }
+
+        /// Performs the action of proposal `id` once enough signers confirmed it (signers only)
+        ///
+        /// With the timelock enabled the action is scheduled after the minimum delay instead.
+        #[ink(message)]
+        pub fn execute(&mut self, id: u64) -> Result<(), PSP22Error> {
+            self.ensure_multisig_signer()?;
+            let proposal = self.multisig_proposal_by_id(id)?;
+            self.ensure_migrated_for(&proposal.action)?;
+
+            // Confirmations of removed signers no longer count
+            let confirmations = proposal
+                .confirmations
+                .iter()
+                .filter(|signer| self.multisig_signers.contains(**signer))
+                .count();
+            if confirmations < self.multisig_threshold as usize {
+                return Err(PSP22Error::NotEnoughConfirmations);
+            }
+
+            self.multisig_proposals.remove(id);
+            if self.timelock_enabled && proposal.action.is_timelocked() {
+                // Approved actions still wait out the minimum delay
+                let eta = self
+                    .env()
+                    .block_timestamp()
+                    .checked_add(self.timelock_min_delay)
+                    .ok_or(PSP22Error::Overflow)?;
+                self.schedule_action(proposal.action, eta)?;
+            } else {
+                self.apply_admin_action(proposal.action)?;
+            }
+
+            self.env().emit_event(MultisigExecuted { id });
+
+            Ok(())
+        }
+
+        // Timelock Functions
+
+        /// Returns whether privileged actions have to be scheduled
+        #[ink(message)]
+        pub fn is_timelock_enabled(&self) -> bool {
+            self.timelock_enabled
+        }
+
+        /// Returns the minimum time between scheduling an action and performing it (ms)
+        #[ink(message)]
+        pub fn timelock_min_delay(&self) -> u64 {
+            self.timelock_min_delay
+        }
+
+        /// Returns the pending operation stored under `id`
+        #[ink(message)]
+        pub fn scheduled_operation(&self, id: u64) -> Option<TimelockOperation> {
+            self.timelock_operations.get(id)
+        }
+
+        /// Returns the number of operations scheduled but not executed or canceled
+        #[ink(message)]
+        pub fn pending_operation_count(&self) -> u32 {
+            self.pending_operation_count.get().unwrap_or_default()
+        }
+
+        /// Returns up to `limit` pending operations and their ids, starting at index `start`
+        ///
+        /// Pages are capped at `MAX_OPERATIONS_PAGE` entries. Removing an operation
+        /// moves the last one into its slot, so the order can change between calls.
+        #[ink(message)]
+        pub fn pending_operations(&self, start: u32, limit: u32) -> Vec<(u64, TimelockOperation)> {
+            let end = start
+                .saturating_add(limit.min(MAX_OPERATIONS_PAGE))
+                .min(self.pending_operation_count());
+
+            (start..end)
+                .filter_map(|index| self.pending_operation_ids.get(index))
+                .filter_map(|id| Some((id, self.timelock_operations.get(id)?)))
+                .collect()
+        }
+
+        /// Subjects privileged actions to a minimum delay of `min_delay` milliseconds (owner only)
+        ///
+        /// Once enabled, privileged messages fail with `Timelocked` and actions
+        /// go through `schedule` and `execute_scheduled`. The timelock can't be
+        /// disabled, and changing the delay is itself timelocked.
+        #[ink(message)]
+        pub fn enable_timelock(&mut self, min_delay: u64) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            self.run_admin_action(AdminAction::EnableTimelock { min_delay })
+        }
+
+        /// Schedules `action` to run from `eta` on, returning the operation id (owner only)
+        ///
+        /// `eta` has to be at least the minimum delay away.
+        #[ink(message)]
+        pub fn schedule(&mut self, action: AdminAction, eta: u64) -> Result<u64, PSP22Error> {
+            self.ensure_owner()?;
+            self.ensure_migrated_for(&action)?;
+            if !self.timelock_enabled {
+                return Err(PSP22Error::TimelockDisabled);
+            }
+
+            let earliest = self
+                .env()
+                .block_timestamp()
+                .checked_add(self.timelock_min_delay)
+                .ok_or(PSP22Error::Overflow)?;
+            if eta < earliest {
+                return Err(PSP22Error::DelayTooShort);
+            }
+
+            self.schedule_action(action, eta)
+        }
+
+        /// Performs scheduled operation `id` once its eta has passed
+        ///
+        /// Anyone can trigger a ready operation.
+        #[ink(message)]
+        pub fn execute_scheduled(&mut self, id: u64) -> Result<(), PSP22Error> {
+            let operation = self
+                .timelock_operations
+                .get(id)
+                .ok_or(PSP22Error::OperationNotFound)?;
+            if self.env().block_timestamp() < operation.eta {
+                return Err(PSP22Error::OperationNotReady);
+            }
+
+            self.timelock_operations.remove(id);
+            self.remove_pending_operation(id);
+            self.apply_admin_action(operation.action)?;
+
+            self.env().emit_event(OperationExecuted { id });
+
+            Ok(())
+        }
+
+        /// Cancels scheduled operation `id` (owner only)
+        ///
+        /// Canceling takes effect immediately, without a delay.
+        #[ink(message)]
+        pub fn cancel_scheduled(&mut self, id: u64) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            self.run_admin_action(AdminAction::CancelScheduled { id })
+        }
+
+        // Governance Functions
+
+        /// Returns the balance of `owner` at the end of `block`
+        ///
+        /// History starts at the block governance was first configured in;
+        /// earlier blocks report zero.
+        #[ink(message)]
+        pub fn balance_of_at(&self, owner: H160, block: u32) -> U256 {
+            match self.checkpoint_start_block.get() {
+                Some(start) if block >= start => (),
+                _ => return U256::from(0),
+            }
+
+            let count = self.balance_checkpoint_counts.get(owner).unwrap_or(0);
+            if count == 0 {
+                // Not moved since history started
+                return self.balance_of(owner);
+            }
+
+            // Find the first checkpoint written after `block`
+            let (mut low, mut high) = (0, count);
+            while low < high {
+                let mid = low + (high - low) / 2;
+                let checkpoint = self.balance_checkpoints.get((owner, mid)).unwrap_or_default();
+                if checkpoint.block > block {
+                    high = mid;
+                } else {
+                    low = mid + 1;
+                }
+            }
+
+            match low.checked_sub(1) {
+                Some(index) => self
+                    .balance_checkpoints
+                    .get((owner, index))
+                    .unwrap_or_default()
+                    .balance,
+                None => U256::from(0),
+            }
+        }
+
+        /// Returns the votes a proposal needs in favor and how many blocks voting lasts
+        #[ink(message)]
+        pub fn governance_settings(&self) -> (U256, u32) {
+            (self.governance_quorum, self.governance_voting_period)
+        }
+
+        /// Returns the voting power a proposer needs at the snapshot
+        #[ink(message)]
+        pub fn proposal_threshold(&self) -> U256 {
+            self.governance_proposal_threshold.get().unwrap_or_default()
+        }
+
+        /// Returns the governance proposal stored under `id`
+        #[ink(message)]
+        pub fn governance_proposal(&self, id: u64) -> Option<GovernanceProposal> {
+            self.governance_proposals.get(id)
+        }
+
+        /// Returns the lifecycle stage of governance proposal `id`
+        #[ink(message)]
+        pub fn proposal_state(&self, id: u64) -> Option<ProposalState> {
+            self.governance_proposals
+                .get(id)
+                .map(|proposal| self.state_of(&proposal))
+        }
+
+        /// Returns how `voter` voted on proposal `id`, if at all
+        #[ink(message)]
+        pub fn vote_of(&self, id: u64, voter: H160) -> Option<bool> {
+            self.governance_votes.get((id, voter))
+        }
+
+        /// Sets the votes in favor a proposal needs and the voting period in blocks (owner only)
+        ///
+        /// Setting a voting period enables governance, which then acts with the
+        /// same powers as the owner. Both values have to be non-zero.
+        #[ink(message)]
+        pub fn set_governance_settings(
+            &mut self,
+            quorum: U256,
+            voting_period: u32,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            self.run_admin_action(AdminAction::SetGovernanceSettings {
+                quorum,
+                voting_period,
+            })
+        }
+
+        /// Sets the voting power needed to create a governance proposal (owner only)
+        #[ink(message)]
+        pub fn set_proposal_threshold(&mut self, threshold: U256) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            self.run_admin_action(AdminAction::SetProposalThreshold { threshold })
+        }
+
+        /// Submits `actions` to a holder vote, returning the proposal id
+        ///
+        /// Votes are weighted by balances at the end of the previous block, and
+        /// the proposer must have held tokens then, at least the proposal threshold.
+        #[ink(message)]
+        pub fn create_proposal(
+            &mut self,
+            description_hash: H256,
+            actions: Vec<AdminAction>,
+        ) -> Result<u64, PSP22Error> {
+            if self.governance_voting_period == 0 {
+                return Err(PSP22Error::GovernanceDisabled);
+            }
+            if actions.is_empty() {
+                return Err(PSP22Error::EmptyProposal);
+            }
+            actions
+                .iter()
+                .try_for_each(|action| self.ensure_migrated_for(action))?;
+
+            let proposer = self.caller();
+            let block = self.env().block_number();
+            let snapshot_block = block.saturating_sub(1);
+            let voting_power = self.balance_of_at(proposer, snapshot_block);
+            if voting_power.is_zero() {
+                return Err(PSP22Error::NoVotingPower);
+            }
+            if voting_power < self.proposal_threshold() {
+                return Err(PSP22Error::BelowProposalThreshold);
+            }
+            let vote_end = block
+                .checked_add(self.governance_voting_period)
+                .ok_or(PSP22Error::Overflow)?;
+
+            let id = self.next_governance_proposal_id;
+            self.next_governance_proposal_id = id.checked_add(1).ok_or(PSP22Error::Overflow)?;
+            self.governance_proposals.insert(
+                id,
+                &GovernanceProposal {
+                    proposer,
+                    description_hash,
+                    actions: actions.clone(),
+                    snapshot_block,
+                    vote_end,
+                    quorum: self.governance_quorum,
+                    for_votes: U256::from(0),
+                    against_votes: U256::from(0),
+                    queued_at: None,
+                    eta: None,
+                    executed: false,
+                },
+            );
+
+            self.env().emit_event(ProposalCreated {
+                id,
+                proposer,
+                description_hash,
+                actions,
+                snapshot_block,
+                vote_end,
+            });
+
+            Ok(id)
+        }
+
+        /// Votes for or against proposal `id` with the caller's snapshot balance
+        #[ink(message)]
+        pub fn cast_vote(&mut self, id: u64, support: bool) -> Result<(), PSP22Error> {
+            let voter = self.caller();
+            let mut proposal = self.governance_proposal_by_id(id)?;
+            proposal
+                .actions
+                .iter()
+                .try_for_each(|action| self.ensure_migrated_for(action))?;
+
+            if self.state_of(&proposal) != ProposalState::Active {
+                return Err(PSP22Error::VotingClosed);
+            }
+            if self.governance_votes.contains((id, voter)) {
+                return Err(PSP22Error::AlreadyVoted);
+            }
+
+            let weight = self.balance_of_at(voter, proposal.snapshot_block);
+            if weight.is_zero() {
+                return Err(PSP22Error::NoVotingPower);
+            }
+
+            // Snapshot balances sum to at most the total supply, so tallies can't overflow
+            if support {
+                proposal.for_votes = proposal.for_votes + weight;
+            } else {
+                proposal.against_votes = proposal.against_votes + weight;
+            }
+            self.governance_proposals.insert(id, &proposal);
+            self.governance_votes.insert((id, voter), &support);
+
+            self.env().emit_event(VoteCast {
+                id,
+                voter,
+                support,
+                weight,
+            });
+
+            Ok(())
+        }
+
+        /// Queues successful proposal `id`, waiting out the timelock delay if enabled
+        #[ink(message)]
+        pub fn queue_proposal(&mut self, id: u64) -> Result<(), PSP22Error> {
+            let mut proposal = self.governance_proposal_by_id(id)?;
+            proposal
+                .actions
+                .iter()
+                .try_for_each(|action| self.ensure_migrated_for(action))?;
+            if self.state_of(&proposal) != ProposalState::Succeeded {
+                return Err(PSP22Error::ProposalNotSucceeded);
+            }
+
+            let delay = if self.timelock_enabled {
+                self.timelock_min_delay
+            } else {
+                0
+            };
+            let now = self.env().block_timestamp();
+            let eta = now.checked_add(delay).ok_or(PSP22Error::Overflow)?;
+            proposal.queued_at = Some(now);
+            proposal.eta = Some(eta);
+            self.governance_proposals.insert(id, &proposal);
+
+            self.env().emit_event(ProposalQueued { id, eta });
+
+            Ok(())
+        }
+
+        /// Performs the actions of queued proposal `id` once its eta has passed
+        ///
+        /// The current timelock delay has to have passed since queuing as well,
+        /// so enabling the timelock or raising the delay also holds back
+        /// proposals that are already queued.
+        #[ink(message)]
+        pub fn execute_proposal(&mut self, id: u64) -> Result<(), PSP22Error> {
+            let mut proposal = self.governance_proposal_by_id(id)?;
+            if self.state_of(&proposal) != ProposalState::Queued {
+                return Err(PSP22Error::ProposalNotQueued);
+            }
+            let now = self.env().block_timestamp();
+            if proposal.eta.is_some_and(|eta| now < eta) {
+                return Err(PSP22Error::OperationNotReady);
+            }
+            if self.timelock_enabled {
+                let ready = proposal
+                    .queued_at
+                    .unwrap_or_default()
+                    .checked_add(self.timelock_min_delay)
+                    .ok_or(PSP22Error::Overflow)?;
+                if now < ready {
+                    return Err(PSP22Error::OperationNotReady);
+                }
+            }
+
+            proposal.executed = true;
+            self.governance_proposals.insert(id, &proposal);
+            for action in proposal.actions {
+                self.apply_admin_action(action)?;
+            }
+
+            self.env().emit_event(ProposalExecuted { id });
+
+            Ok(())
+        }
+
+        // Holder Functions
+
+        /// Returns the number of accounts with a non-zero balance
+        #[ink(message)]
+        pub fn holder_count(&self) -> u32 {
+            self.holder_count
+        }
+
+        /// Returns up to `limit` holders and their balances, starting at index `start`
+        ///
+        /// Pages are capped at `MAX_HOLDERS_PAGE` entries. Removing a holder moves
+        /// the last one into its slot, so the order can change between calls.
+        #[ink(message)]
+        pub fn holders(&self, start: u32, limit: u32) -> Vec<(H160, U256)> {
+            let end = start
+                .saturating_add(limit.min(MAX_HOLDERS_PAGE))
+                .min(self.holder_count);
+
+            (start..end)
+                .filter_map(|index| self.holders.get(index))
+                .map(|holder| (holder, self.account(holder).balance))
+                .collect()
+        }
+
+        // EIP-3009 Functions
+
+        /// Returns the chain id this deployment signs messages for, or 0 if it has none
+        #[ink(message)]
+        pub fn chain_id(&self) -> u64 {
+            self.chain_id.get().unwrap_or_default()
+        }
+
+        /// Returns whether `authorizer` already used or canceled `nonce`
+        #[ink(message)]
+        pub fn authorization_state(&self, authorizer: H160, nonce: H256) -> bool {
+            self.authorization_states.contains((authorizer, nonce))
+        }
+
+        /// Transfers `value` from `from` to `to` with a signed authorization from `from`
+        ///
+        /// Anyone can submit the authorization, which is valid strictly between
+        /// `valid_after` and `valid_before`. Like in EIP-3009 these are Unix
+        /// timestamps in seconds, not the milliseconds of block timestamps.
+        #[ink(message)]
+        #[allow(clippy::too_many_arguments)]
+        pub fn transfer_with_authorization(
+            &mut self,
+            from: H160,
+            to: H160,
+            value: U256,
+            valid_after: u64,
+            valid_before: u64,
+            nonce: H256,
+            v: u8,
+            r: H256,
+            s: H256,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            self.use_authorization(
+                TRANSFER_WITH_AUTHORIZATION_TYPE,
+                from,
+                to,
+                value,
+                valid_after,
+                valid_before,
+                nonce,
+                v,
+                r,
+                s,
+            )
+        }
+
+        /// Like `transfer_with_authorization`, but only the payee `to` can submit it
+        ///
+        /// Prevents front-running a deposit contract that pulls the authorized payment.
+        #[ink(message)]
+        #[allow(clippy::too_many_arguments)]
+        pub fn receive_with_authorization(
+            &mut self,
+            from: H160,
+            to: H160,
+            value: U256,
+            valid_after: u64,
+            valid_before: u64,
+            nonce: H256,
+            v: u8,
+            r: H256,
+            s: H256,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            if self.caller() != to {
+                return Err(PSP22Error::Unauthorized);
+            }
+
+            self.use_authorization(
+                RECEIVE_WITH_AUTHORIZATION_TYPE,
+                from,
+                to,
+                value,
+                valid_after,
+                valid_before,
+                nonce,
+                v,
+                r,
+                s,
+            )
+        }
+
+        /// Cancels an unused authorization of `authorizer`, signed by `authorizer`
+        #[ink(message)]
+        pub fn cancel_authorization(
+            &mut self,
+            authorizer: H160,
+            nonce: H256,
+            v: u8,
+            r: H256,
+            s: H256,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            self.ensure_chain_id()?;
+            if self.authorization_states.contains((authorizer, nonce)) {
+                return Err(PSP22Error::AuthorizationAlreadyUsed);
+            }
+
+            let digest = self.eip712_digest(
+                CANCEL_AUTHORIZATION_TYPE,
+                &[Self::address_word(authorizer), nonce.0],
+            );
+            if self.recover_vrs(&digest, v, r, s)? != authorizer {
+                return Err(PSP22Error::InvalidSignature);
+            }
+
+            self.authorization_states.insert((authorizer, nonce), &());
+
+            self.env().emit_event(AuthorizationCanceled { authorizer, nonce });
+
+            Ok(())
+        }
+
+        // Operator Functions
+
+        /// Returns the operators trusted by every holder that hasn't revoked them
+        #[ink(message)]
+        pub fn default_operators(&self) -> Vec<H160> {
+            self.default_operator_list.get().unwrap_or_default()
+        }
+
+        /// Returns whether `operator` can move all tokens of `holder`
+        ///
+        /// Holders are always operators for themselves.
+        #[ink(message)]
+        pub fn is_operator_for(&self, operator: H160, holder: H160) -> bool {
+            if operator == holder {
+                return true;
+            }
+            // Tokens in custody belong to escrows, streams and stakes
+            if holder == self.env().address() {
+                return false;
+            }
+            if self.default_operators.contains(operator) {
+                return !self.revoked_default_operators.contains((holder, operator));
+            }
+            self.operators.contains((holder, operator))
+        }
+
+        /// Lets `operator` move any amount of the caller's tokens
+        #[ink(message)]
+        pub fn authorize_operator(&mut self, operator: H160) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            self.ensure_not_zero(operator)?;
+            let holder = self.caller();
+            if operator == holder {
+                return Err(PSP22Error::InvalidOperator);
+            }
+
+            if self.default_operators.contains(operator) {
+                self.revoked_default_operators.remove((holder, operator));
+            } else {
+                self.operators.insert((holder, operator), &());
+            }
+
+            self.env().emit_event(AuthorizedOperator { operator, holder });
+
+            Ok(())
+        }
+
+        /// Withdraws the caller's authorization of `operator`, default operators included
+        #[ink(message)]
+        pub fn revoke_operator(&mut self, operator: H160) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            let holder = self.caller();
+            if operator == holder {
+                return Err(PSP22Error::InvalidOperator);
+            }
+
+            if self.default_operators.contains(operator) {
+                self.revoked_default_operators.insert((holder, operator), &());
+            } else {
+                self.operators.remove((holder, operator));
+            }
+
+            self.env().emit_event(RevokedOperator { operator, holder });
+
+            Ok(())
+        }
+
+        /// Moves `value` tokens of `from` to `to` on behalf of `from` (operators only)
+        #[ink(message)]
+        pub fn operator_send(
+            &mut self,
+            from: H160,
+            to: H160,
+            value: U256,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            if !self.is_operator_for(self.caller(), from) {
+                return Err(PSP22Error::Unauthorized);
+            }
+
+            self.transfer_from_to(from, to, value)
+        }
+
+        // Vault Functions (ERC-4626)
+        //
+        // MEME is the underlying asset and vault shares are kept in their own
+        // ledger. Conversions round in favor of the vault: deposits and
+        // redemptions round down, mints and withdrawals round up.
+
+        /// Returns the MEME held by the vault for shareholders
+        #[ink(message)]
+        pub fn total_assets(&self) -> U256 {
+            self.vault_assets
+        }
+
+        /// Returns the shares `assets` are worth, rounded down
+        #[ink(message)]
+        pub fn convert_to_shares(&self, assets: U256) -> Result<U256, PSP22Error> {
+            self.assets_to_shares(assets, false)
+        }
+
+        /// Returns the assets `shares` are worth, rounded down
+        #[ink(message)]
+        pub fn convert_to_assets(&self, shares: U256) -> Result<U256, PSP22Error> {
+            self.shares_to_assets(shares, false)
+        }
+
+        /// Returns the most assets `receiver` can deposit
+        #[ink(message)]
+        pub fn max_deposit(&self, _receiver: H160) -> U256 {
+            U256::MAX
+        }
+
+        /// Returns the most shares `receiver` can mint
+        #[ink(message)]
+        pub fn max_mint(&self, _receiver: H160) -> U256 {
+            U256::MAX
+        }
+
+        /// Returns the most assets `owner` can withdraw
+        #[ink(message)]
+        pub fn max_withdraw(&self, owner: H160) -> Result<U256, PSP22Error> {
+            self.shares_to_assets(self.share_balance_of(owner), false)
+        }
+
+        /// Returns the most shares `owner` can redeem
+        #[ink(message)]
+        pub fn max_redeem(&self, owner: H160) -> U256 {
+            self.share_balance_of(owner)
+        }
+
+        /// Returns the shares `vault_deposit(assets)` would mint
+        #[ink(message)]
+        pub fn preview_deposit(&self, assets: U256) -> Result<U256, PSP22Error> {
+            self.assets_to_shares(assets, false)
+        }
+
+        /// Returns the assets `vault_mint(shares)` would take
+        #[ink(message)]
+        pub fn preview_mint(&self, shares: U256) -> Result<U256, PSP22Error> {
+            self.shares_to_assets(shares, true)
+        }
+
+        /// Returns the shares `vault_withdraw(assets)` would burn
+        #[ink(message)]
+        pub fn preview_withdraw(&self, assets: U256) -> Result<U256, PSP22Error> {
+            self.assets_to_shares(assets, true)
+        }
+
+        /// Returns the assets `vault_redeem(shares)` would pay out
+        #[ink(message)]
+        pub fn preview_redeem(&self, shares: U256) -> Result<U256, PSP22Error> {
+            self.shares_to_assets(shares, false)
+        }
+
+        /// Deposits `assets` of the caller's MEME and mints the shares to `receiver`
+        #[ink(message)]
+        pub fn vault_deposit(&mut self, assets: U256, receiver: H160) -> Result<U256, PSP22Error> {
+            self.ensure_migrated()?;
+            let shares = self.preview_deposit(assets)?;
+            self.vault_enter(receiver, assets, shares)?;
+            Ok(shares)
+        }
+
+        /// Mints exactly `shares` to `receiver` for the caller's MEME
+        #[ink(message)]
+        pub fn vault_mint(&mut self, shares: U256, receiver: H160) -> Result<U256, PSP22Error> {
+            self.ensure_migrated()?;
+            let assets = self.preview_mint(shares)?;
+            self.vault_enter(receiver, assets, shares)?;
+            Ok(assets)
+        }
+
+        /// Pays exactly `assets` to `receiver`, burning shares of `owner`
+        #[ink(message)]
+        pub fn vault_withdraw(
+            &mut self,
+            assets: U256,
+            receiver: H160,
+            owner: H160,
+        ) -> Result<U256, PSP22Error> {
+            self.ensure_migrated()?;
+            let shares = self.preview_withdraw(assets)?;
+            self.vault_exit(receiver, owner, assets, shares)?;
+            Ok(shares)
+        }
+
+        /// Burns `shares` of `owner` and pays their assets to `receiver`
+        #[ink(message)]
+        pub fn vault_redeem(
+            &mut self,
+            shares: U256,
+            receiver: H160,
+            owner: H160,
+        ) -> Result<U256, PSP22Error> {
+            self.ensure_migrated()?;
+            let assets = self.preview_redeem(shares)?;
+            self.vault_exit(receiver, owner, assets, shares)?;
+            Ok(assets)
+        }
+
+        /// Adds `assets` of the caller's MEME to the vault, raising the share price
+        #[ink(message)]
+        pub fn deposit_vault_yield(&mut self, assets: U256) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            if assets.is_zero() {
+                return Err(PSP22Error::ZeroAmount);
+            }
+
+            let from = self.caller();
+            self.lock_in_custody(from, assets)?;
+            self.vault_assets = self
+                .vault_assets
+                .checked_add(assets)
+                .ok_or(PSP22Error::Overflow)?;
+
+            self.env().emit_event(VaultYieldDeposited { from, assets });
+
+            Ok(())
+        }
+
+        /// Returns the total number of vault shares
+        #[ink(message)]
+        pub fn share_total_supply(&self) -> U256 {
+            self.vault_total_shares
+        }
+
+        /// Returns the vault shares of `owner`
+        #[ink(message)]
+        pub fn share_balance_of(&self, owner: H160) -> U256 {
+            self.vault_shares.get(owner).unwrap_or_default()
+        }
+
+        /// Returns the vault shares `spender` may move for `owner`
+        #[ink(message)]
+        pub fn share_allowance(&self, owner: H160, spender: H160) -> U256 {
+            self.vault_share_allowances
+                .get((owner, spender))
+                .unwrap_or_default()
+        }
+
+        /// Transfers vault shares from the caller to `to`
+        #[ink(message)]
+        pub fn share_transfer(&mut self, to: H160, value: U256) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            let from = self.caller();
+            self.transfer_shares(from, to, value)
+        }
+
+        /// Lets `spender` move up to `value` of the caller's vault shares
+        #[ink(message)]
+        pub fn share_approve(&mut self, spender: H160, value: U256) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            self.ensure_not_zero(spender)?;
+            let owner = self.caller();
+            if owner == spender {
+                return Ok(());
+            }
+
+            self.set_share_allowance(owner, spender, value);
+
+            Ok(())
+        }
+
+        /// Transfers vault shares of `from` to `to` using the caller's share allowance
+        #[ink(message)]
+        pub fn share_transfer_from(
+            &mut self,
+            from: H160,
+            to: H160,
+            value: U256,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            let spender = self.caller();
+            if from == to || value.is_zero() {
+                return Ok(());
+            }
+
+            self.spend_share_allowance(from, spender, value)?;
+            self.transfer_shares(from, to, value)
+        }
+
+        // Bridge Functions
+
+        /// Returns whether `account` is a bridge relayer
+        #[ink(message)]
+        pub fn is_relayer(&self, account: H160) -> bool {
+            self.bridge_relayers.contains(account)
+        }
+
+        /// Returns the number of relayers and how many of them must attest a transfer
+        #[ink(message)]
+        pub fn relayer_threshold(&self) -> (u32, u32) {
+            (self.bridge_threshold, self.bridge_relayer_count)
+        }
+
+        /// Returns the inbound rate limit of `chain`, if the chain is supported
+        #[ink(message)]
+        pub fn bridge_rate_limit(&self, chain: u64) -> Option<BridgeRateLimit> {
+            self.bridge_rate_limits.get(chain)
+        }
+
+        /// Returns the nonce the next transfer to `chain` will carry
+        #[ink(message)]
+        pub fn outbound_nonce(&self, chain: u64) -> u64 {
+            self.bridge_outbound_nonces.get(chain).unwrap_or(0)
+        }
+
+        /// Returns whether the transfer `nonce` from `chain` was already minted
+        #[ink(message)]
+        pub fn is_bridge_message_processed(&self, chain: u64, nonce: u64) -> bool {
+            self.bridge_processed.contains((chain, nonce))
+        }
+
+        /// Adds `relayer` to the accounts attesting inbound transfers (owner only)
+        #[ink(message)]
+        pub fn add_relayer(&mut self, relayer: H160) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            self.run_admin_action(AdminAction::AddRelayer { relayer })
+        }
+
+        /// Removes `relayer` from the accounts attesting inbound transfers (owner only)
+        #[ink(message)]
+        pub fn remove_relayer(&mut self, relayer: H160) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            self.run_admin_action(AdminAction::RemoveRelayer { relayer })
+        }
+
+        /// Sets how many relayers must attest an inbound transfer (owner only)
+        #[ink(message)]
+        pub fn set_relayer_threshold(&mut self, threshold: u32) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            self.run_admin_action(AdminAction::SetRelayerThreshold { threshold })
+        }
+
+        /// Limits minting from `chain` to `limit` tokens per `period` milliseconds (owner only)
+        ///
+        /// A chain is supported in both directions while it has a limit; a zero
+        /// `limit` removes it. A non-zero `limit` needs a non-zero `period`.
+        #[ink(message)]
+        pub fn set_bridge_rate_limit(
+            &mut self,
+            chain: u64,
+            limit: U256,
+            period: u64,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            self.run_admin_action(AdminAction::SetBridgeRateLimit {
+                chain,
+                limit,
+                period,
+            })
+        }
+
+        /// Burns `amount` of the caller's tokens to be minted to `recipient` on `dest_chain`
+        ///
+        /// Returns the outbound nonce relayers attest the transfer with.
+        #[ink(message)]
+        pub fn bridge_out(
+            &mut self,
+            dest_chain: u64,
+            recipient: Vec<u8>,
+            amount: U256,
+        ) -> Result<u64, PSP22Error> {
+            self.ensure_migrated()?;
+            self.ensure_not_wrapped()?;
+            if !self.bridge_rate_limits.contains(dest_chain) {
+                return Err(PSP22Error::UnsupportedChain);
+            }
+            if recipient.is_empty() {
+                return Err(PSP22Error::InvalidRecipient);
+            }
+            if amount.is_zero() {
+                return Err(PSP22Error::ZeroAmount);
+            }
+
+            let sender = self.caller();
+            self.burn_from(sender, amount)?;
+
+            let nonce = self.outbound_nonce(dest_chain);
+            self.bridge_outbound_nonces.insert(
+                dest_chain,
+                &nonce.checked_add(1).ok_or(PSP22Error::Overflow)?,
+            );
+
+            self.env().emit_event(BridgeOut {
+                dest_chain,
+                nonce,
+                sender,
+                recipient,
+                amount,
+            });
+
+            Ok(nonce)
+        }
+
+        /// Mints `amount` to `recipient` for transfer `nonce` burned on `src_chain`
+        ///
+        /// Needs signatures of at least the threshold of distinct relayers over
+        /// the bridge message hash. Each transfer is minted once, and minting per
+        /// chain is capped by its rate limit.
+        #[ink(message)]
+        pub fn bridge_in(
+            &mut self,
+            src_chain: u64,
+            nonce: u64,
+            recipient: H160,
+            amount: U256,
+            signatures: Vec<[u8; 65]>,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_migrated()?;
+            self.ensure_not_wrapped()?;
+            self.ensure_chain_id()?;
+            let mut rate_limit = self
+                .bridge_rate_limits
+                .get(src_chain)
+                .ok_or(PSP22Error::UnsupportedChain)?;
+            if self.bridge_processed.contains((src_chain, nonce)) {
+                return Err(PSP22Error::BridgeMessageProcessed);
+            }
+
+            let message_hash = self.bridge_message_hash(src_chain, nonce, recipient, amount);
+            let mut attesters: Vec<H160> = Vec::new();
+            for signature in &signatures {
+                // Signatures of non-relayers and repeated relayers don't count
+                let Ok(signer) = self.recover_signer(&message_hash, signature) else {
+                    continue;
+                };
+                if self.bridge_relayers.contains(signer) && !attesters.contains(&signer) {
+                    attesters.push(signer);
+                }
+            }
+            if self.bridge_threshold == 0 || attesters.len() < self.bridge_threshold as usize {
+                return Err(PSP22Error::NotEnoughAttestations);
+            }
+
+            rate_limit.consume(self.env().block_timestamp(), amount)?;
+            self.bridge_rate_limits.insert(src_chain, &rate_limit);
+            self.bridge_processed.insert((src_chain, nonce), &());
+            self.mint_to(recipient, amount)?;
+
+            self.env().emit_event(BridgeIn {
+                src_chain,
+                nonce,
+                recipient,
+                amount,
+            });
+
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink::env::test;
+
+        const CHAIN_ID: u64 = 1;
+
+        fn default_accounts() -> test::DefaultAccounts {
+            test::default_accounts()
+        }
+
+        fn set_caller(caller: H160) {
+            test::set_caller(caller);
+        }
+
+        fn set_block_timestamp(timestamp: u64) {
+            test::set_block_timestamp::<ink::env::DefaultEnvironment>(timestamp);
+        }
+
+        fn advance_blocks(blocks: u32) {
+            for _ in 0..blocks {
+                test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+        }
+
+        /// Address derived from a test secret key
+        fn address_of(secret: [u8; 32]) -> H160 {
+            let secp = secp256k1::Secp256k1::new();
+            let key = secp256k1::SecretKey::from_slice(&secret).unwrap();
+            let public_key = secp256k1::PublicKey::from_secret_key(&secp, &key).serialize();
+
+            let mut address = [0u8; 20];
+            ink::env::ecdsa_to_eth_address(&public_key, &mut address).unwrap();
+            H160::from(address)
+        }
+
+        /// Signs `message_hash` with a test secret key
+        fn sign(secret: [u8; 32], message_hash: [u8; 32]) -> [u8; 65] {
+            let secp = secp256k1::Secp256k1::new();
+            let key = secp256k1::SecretKey::from_slice(&secret).unwrap();
+            let message = secp256k1::Message::from_digest(message_hash);
+            let (recovery_id, compact) = secp
+                .sign_ecdsa_recoverable(&message, &key)
+                .serialize_compact();
+
+            let mut signature = [0u8; 65];
+            signature[..64].copy_from_slice(&compact);
+            signature[64] = recovery_id.to_i32() as u8;
+            signature
+        }
+
+        /// Splits a test signature into the Ethereum `(v, r, s)` form
+        fn split_signature(signature: [u8; 65]) -> (u8, H256, H256) {
+            (
+                signature[64] + 27,
+                H256::from_slice(&signature[..32]),
+                H256::from_slice(&signature[32..64]),
+            )
+        }
+
+        #[ink::test]
+        fn new_works() {
+            let token = PspCoin::new();
+            assert_eq!(token.total_supply(), U256::from(0));
+            assert_eq!(token.name(), Some(String::from("MemeCoin")));
+            assert_eq!(token.symbol(), Some(String::from("MEME")));
+            assert_eq!(token.decimals(), 18);
+        }
+
+        #[ink::test]
+        fn new_with_supply_works() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let initial_supply = U256::from(1000000);
+            let token = PspCoin::new_with_supply(initial_supply);
+
+            assert_eq!(token.total_supply(), initial_supply);
+            assert_eq!(token.balance_of(accounts.alice), initial_supply);
+            assert_eq!(token.balance_of(accounts.bob), U256::from(0));
+        }
+
+        #[ink::test]
+        fn transfer_works() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let initial_supply = U256::from(1000);
+            let mut token = PspCoin::new_with_supply(initial_supply);
+
+            let transfer_amount = U256::from(100);
+            assert!(
+                token
+                    .transfer(accounts.bob, transfer_amount, Vec::new())
+                    .is_ok()
+            );
+
+            assert_eq!(token.balance_of(accounts.alice), U256::from(900));
+            assert_eq!(token.balance_of(accounts.bob), U256::from(100));
+        }
+
+        #[ink::test]
+        fn transfer_insufficient_balance_fails() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let initial_supply = U256::from(100);
+            let mut token = PspCoin::new_with_supply(initial_supply);
+
+            let transfer_amount = U256::from(200);
+            assert_eq!(
+                token.transfer(accounts.bob, transfer_amount, Vec::new()),
+                Err(PSP22Error::InsufficientBalance)
+            );
+        }
+
+        #[ink::test]
+        fn transfer_to_self_works() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let initial_supply = U256::from(1000);
+            let mut token = PspCoin::new_with_supply(initial_supply);
+
+            assert!(
+                token
+                    .transfer(accounts.alice, U256::from(100), Vec::new())
+                    .is_ok()
+            );
+            assert_eq!(token.balance_of(accounts.alice), initial_supply);
+        }
+
+        #[ink::test]
+        fn approve_works() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+
+            let allowance_amount = U256::from(200);
+            assert!(token.approve(accounts.bob, allowance_amount).is_ok());
+
+            assert_eq!(
+                token.allowance(accounts.alice, accounts.bob),
+                allowance_amount
+            );
+        }
+
+        #[ink::test]
+        fn approve_self_works() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+
+            assert!(token.approve(accounts.alice, U256::from(100)).is_ok());
+            assert_eq!(
+                token.allowance(accounts.alice, accounts.alice),
+                U256::from(0)
+            );
+        }
+
+        #[ink::test]
+        fn transfer_from_works() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+
+            // Alice approves Bob to spend 200 tokens
+            assert!(token.approve(accounts.bob, U256::from(200)).is_ok());
+
+            // Bob transfers 100 tokens from Alice to Charlie
+            set_caller(accounts.bob);
+            assert!(
+                token
+                    .transfer_from(
+                        accounts.alice,
+                        accounts.charlie,
+                        U256::from(100),
+                        Vec::new()
+                    )
+                    .is_ok()
+            );
+
+            assert_eq!(token.balance_of(accounts.alice), U256::from(900));
+            assert_eq!(token.balance_of(accounts.charlie), U256::from(100));
+            assert_eq!(
+                token.allowance(accounts.alice, accounts.bob),
+                U256::from(100)
+            );
+        }
+
+        #[ink::test]
+        fn transfer_from_insufficient_allowance_fails() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+
+            // Alice approves Bob to spend 50 tokens
+            assert!(token.approve(accounts.bob, U256::from(50)).is_ok());
+
+            // Bob tries to transfer 100 tokens from Alice
+            set_caller(accounts.bob);
+            assert_eq!(
+                token.transfer_from(
+                    accounts.alice,
+                    accounts.charlie,
+                    U256::from(100),
+                    Vec::new()
+                ),
+                Err(PSP22Error::InsufficientAllowance)
+            );
+        }
+
+        #[ink::test]
+        fn transfer_from_by_owner_works() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+
+            // Alice transfers her own tokens without approval
+            assert!(
+                token
+                    .transfer_from(accounts.alice, accounts.bob, U256::from(100), Vec::new())
+                    .is_ok()
+            );
+
+            assert_eq!(token.balance_of(accounts.alice), U256::from(900));
+            assert_eq!(token.balance_of(accounts.bob), U256::from(100));
+        }
+
+        #[ink::test]
+        fn increase_allowance_works() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+
+            assert!(token.approve(accounts.bob, U256::from(100)).is_ok());
+            assert!(
+                token
+                    .increase_allowance(accounts.bob, U256::from(50))
+                    .is_ok()
+            );
+
+            assert_eq!(
+                token.allowance(accounts.alice, accounts.bob),
+                U256::from(150)
+            );
+        }
+
+        #[ink::test]
+        fn decrease_allowance_works() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+
+            assert!(token.approve(accounts.bob, U256::from(100)).is_ok());
+            assert!(
+                token
+                    .decrease_allowance(accounts.bob, U256::from(50))
+                    .is_ok()
+            );
+
+            assert_eq!(
+                token.allowance(accounts.alice, accounts.bob),
+                U256::from(50)
+            );
+        }
+
+        #[ink::test]
+        fn decrease_allowance_below_zero_fails() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+
+            assert!(token.approve(accounts.bob, U256::from(50)).is_ok());
+            assert_eq!(
+                token.decrease_allowance(accounts.bob, U256::from(100)),
+                Err(PSP22Error::InsufficientAllowance)
+            );
+        }
+
+        #[ink::test]
+        fn mint_works() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new();
+
+            assert!(token.mint(U256::from(1000)).is_ok());
+            assert_eq!(token.total_supply(), U256::from(1000));
+            assert_eq!(token.balance_of(accounts.alice), U256::from(1000));
+
+            assert!(token.mint(U256::from(500)).is_ok());
+            assert_eq!(token.total_supply(), U256::from(1500));
+            assert_eq!(token.balance_of(accounts.alice), U256::from(1500));
+        }
+
+        #[ink::test]
+        fn mint_zero_is_noop() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new();
+
+            assert!(token.mint(U256::from(0)).is_ok());
+            assert_eq!(token.total_supply(), U256::from(0));
+            assert_eq!(token.balance_of(accounts.alice), U256::from(0));
+        }
+
+        #[ink::test]
+        fn burn_works() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+
+            assert!(token.burn(U256::from(300)).is_ok());
+            assert_eq!(token.total_supply(), U256::from(700));
+            assert_eq!(token.balance_of(accounts.alice), U256::from(700));
+        }
+
+        #[ink::test]
+        fn burn_insufficient_balance_fails() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(100));
+
+            assert_eq!(
+                token.burn(U256::from(200)),
+                Err(PSP22Error::InsufficientBalance)
+            );
+        }
+
+        #[ink::test]
+        fn burn_zero_is_noop() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+
+            assert!(token.burn(U256::from(0)).is_ok());
+            assert_eq!(token.total_supply(), U256::from(1000));
+        }
+
+        #[ink::test]
+        fn metadata_works() {
+            let token = PspCoin::new();
+
+            assert_eq!(token.name(), Some(String::from("MemeCoin")));
+            assert_eq!(token.symbol(), Some(String::from("MEME")));
+            assert_eq!(token.decimals(), 18);
+        }
+
+        const VOUCHER_KEY: [u8; 32] = [7u8; 32];
+
+        /// Token on `CHAIN_ID` that accepts vouchers signed with `VOUCHER_KEY`
+        fn new_voucher_token() -> PspCoin {
+            let mut token = PspCoin::new_with_chain_id(U256::from(0), CHAIN_ID);
+            assert!(token.add_voucher_signer(address_of(VOUCHER_KEY)).is_ok());
+            token
+        }
+
+        #[ink::test]
+        fn voucher_signer_management_is_owner_only() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new();
+            let signer = address_of(VOUCHER_KEY);
+
+            assert!(token.add_voucher_signer(signer).is_ok());
+            assert!(token.is_voucher_signer(signer));
+
+            set_caller(accounts.bob);
+            assert_eq!(token.remove_voucher_signer(signer), Err(PSP22Error::Unauthorized));
+            assert!(token.is_voucher_signer(signer));
+        }
+
+        #[ink::test]
+        fn redeem_voucher_works() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = new_voucher_token();
+
+            let amount = U256::from(250);
+            let signature = sign(
+                VOUCHER_KEY,
+                token.voucher_hash(accounts.bob, amount, 1, 1_000),
+            );
+
+            // Anyone may submit the voucher on behalf of the recipient
+            set_caller(accounts.charlie);
+            assert!(
+                token
+                    .redeem_voucher(accounts.bob, amount, 1, 1_000, signature)
+                    .is_ok()
+            );
+
+            assert_eq!(token.balance_of(accounts.bob), amount);
+            assert_eq!(token.total_supply(), amount);
+            assert!(token.is_voucher_nonce_used(1));
+        }
+
+        #[ink::test]
+        fn redeem_voucher_twice_fails() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = new_voucher_token();
+
+            let amount = U256::from(250);
+            let signature = sign(
+                VOUCHER_KEY,
+                token.voucher_hash(accounts.bob, amount, 1, 1_000),
+            );
+
+            assert!(
+                token
+                    .redeem_voucher(accounts.bob, amount, 1, 1_000, signature)
+                    .is_ok()
+            );
+            assert_eq!(
+                token.redeem_voucher(accounts.bob, amount, 1, 1_000, signature),
+                Err(PSP22Error::VoucherAlreadyRedeemed)
+            );
+            assert_eq!(token.balance_of(accounts.bob), amount);
+        }
+
+        #[ink::test]
+        fn redeem_expired_voucher_fails() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = new_voucher_token();
+
+            let amount = U256::from(250);
+            let signature = sign(
+                VOUCHER_KEY,
+                token.voucher_hash(accounts.bob, amount, 1, 1_000),
+            );
+
+            set_block_timestamp(1_001);
+            assert_eq!(
+                token.redeem_voucher(accounts.bob, amount, 1, 1_000, signature),
+                Err(PSP22Error::VoucherExpired)
+            );
+        }
+
+        #[ink::test]
+        fn voucher_expires_at_expiry() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = new_voucher_token();
+
+            let amount = U256::from(250);
+            let signature = sign(
+                VOUCHER_KEY,
+                token.voucher_hash(accounts.bob, amount, 1, 1_000),
+            );
+
+            // Same rule as allowances: expired once the timestamp reaches `expiry`
+            set_block_timestamp(1_000);
+            assert_eq!(
+                token.redeem_voucher(accounts.bob, amount, 1, 1_000, signature),
+                Err(PSP22Error::VoucherExpired)
+            );
+
+            set_block_timestamp(999);
+            assert!(
+                token
+                    .redeem_voucher(accounts.bob, amount, 1, 1_000, signature)
+                    .is_ok()
+            );
+        }
+
+        #[ink::test]
+        fn voucher_is_bound_to_chain_id() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = new_voucher_token();
+
+            let amount = U256::from(250);
+            let signature = sign(
+                VOUCHER_KEY,
+                token.voucher_hash(accounts.bob, amount, 1, 1_000),
+            );
+
+            // The same contract address deployed on another chain
+            token.chain_id.set(&(CHAIN_ID + 1));
+            assert_eq!(
+                token.redeem_voucher(accounts.bob, amount, 1, 1_000, signature),
+                Err(PSP22Error::InvalidSignature)
+            );
+
+            // A token without a chain id doesn't take vouchers at all
+            let mut token = PspCoin::new();
+            assert!(token.add_voucher_signer(address_of(VOUCHER_KEY)).is_ok());
+            assert_eq!(
+                token.redeem_voucher(accounts.bob, amount, 1, 1_000, signature),
+                Err(PSP22Error::ChainIdNotSet)
+            );
+        }
+
+        #[ink::test]
+        fn voucher_signer_changes_emit_events() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new();
+            let signer = address_of(VOUCHER_KEY);
+
+            let events_before = test::recorded_events().into_iter().count();
+            assert!(token.add_voucher_signer(signer).is_ok());
+            assert!(token.remove_voucher_signer(signer).is_ok());
+            assert!(!token.is_voucher_signer(signer));
+
+            // One `VoucherSignerChanged` event per change
+            assert_eq!(
+                test::recorded_events().into_iter().count(),
+                events_before + 2
+            );
+        }
+
+        #[ink::test]
+        fn redeem_voucher_from_unknown_signer_fails() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = new_voucher_token();
+
+            let amount = U256::from(250);
+            let signature = sign(
+                [9u8; 32],
+                token.voucher_hash(accounts.bob, amount, 1, 1_000),
+            );
+
+            assert_eq!(
+                token.redeem_voucher(accounts.bob, amount, 1, 1_000, signature),
+                Err(PSP22Error::InvalidSignature)
+            );
+
+            // A valid signature over different terms is rejected as well
+            let signature = sign(
+                VOUCHER_KEY,
+                token.voucher_hash(accounts.bob, amount, 1, 1_000),
+            );
+            assert_eq!(
+                token.redeem_voucher(accounts.bob, U256::from(1_000), 1, 1_000, signature),
+                Err(PSP22Error::InvalidSignature)
+            );
+            assert_eq!(token.total_supply(), U256::from(0));
+        }
+
+        #[ink::test]
+        fn upgrade_by_non_owner_fails() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new();
+
+            set_caller(accounts.bob);
+            assert_eq!(token.upgrade(H256::repeat_byte(1)), Err(PSP22Error::Unauthorized));
+        }
+
+        #[ink::test]
+        fn migrate_on_current_storage_fails() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new();
+
+            assert_eq!(token.storage_version(), STORAGE_VERSION);
+            assert_eq!(token.migrate(), Err(PSP22Error::AlreadyMigrated));
+        }
+
+        #[ink::test]
+        fn migrate_runs_once() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new();
+            // Simulate storage written by an older release
+            token.storage_version = STORAGE_VERSION - 1;
+
+            set_caller(accounts.bob);
+            assert_eq!(token.migrate(), Err(PSP22Error::Unauthorized));
+
+            set_caller(accounts.alice);
+            assert!(token.migrate().is_ok());
+            assert_eq!(token.storage_version(), STORAGE_VERSION);
+            assert_eq!(token.migrate(), Err(PSP22Error::AlreadyMigrated));
+        }
+
+        #[ink::test]
+        fn messages_wait_for_migrate() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            // Simulate code upgraded over storage of an older release
+            token.storage_version = STORAGE_VERSION - 1;
+
+            assert_eq!(
+                token.transfer(accounts.bob, U256::from(100), Vec::new()),
+                Err(PSP22Error::MigrationRequired)
+            );
+            assert_eq!(
+                token.approve(accounts.bob, U256::from(100)),
+                Err(PSP22Error::MigrationRequired)
+            );
+            assert_eq!(
+                token.burn(U256::from(100)),
+                Err(PSP22Error::MigrationRequired)
+            );
+            assert_eq!(
+                token.stake(U256::from(100)),
+                Err(PSP22Error::MigrationRequired)
+            );
+            // Privileged messages too, through `apply_admin_action`
+            assert_eq!(
+                token.mint(U256::from(100)),
+                Err(PSP22Error::MigrationRequired)
+            );
+            assert_eq!(
+                token.add_voucher_signer(accounts.bob),
+                Err(PSP22Error::MigrationRequired)
+            );
+            // Queries keep working
+            assert_eq!(token.balance_of(accounts.alice), U256::from(1000));
+
+            assert!(token.migrate().is_ok());
+            assert!(
+                token
+                    .transfer(accounts.bob, U256::from(100), Vec::new())
+                    .is_ok()
+            );
+            assert!(token.mint(U256::from(100)).is_ok());
+        }
+
+        #[ink::test]
+        fn migrate_skips_the_timelock() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new();
+            assert!(token.enable_timelock(1_000).is_ok());
+            token.storage_version = STORAGE_VERSION - 1;
+
+            // Nothing else can be scheduled before the migration
+            let action = AdminAction::SetUnbondingPeriod {
+                unbonding_period: 1,
+            };
+            assert_eq!(
+                token.schedule(action, 1_000),
+                Err(PSP22Error::MigrationRequired)
+            );
+
+            // The upgrade already waited out the delay
+            assert!(token.migrate().is_ok());
+            assert_eq!(token.storage_version(), STORAGE_VERSION);
+        }
+
+        #[ink::test]
+        fn multisig_can_only_migrate_before_migration() {
+            let accounts = default_accounts();
+            let mut token = new_multisig_token();
+            token.storage_version = STORAGE_VERSION - 1;
+
+            assert_eq!(
+                token.propose(AdminAction::Mint {
+                    to: accounts.django,
+                    value: U256::from(100)
+                }),
+                Err(PSP22Error::MigrationRequired)
+            );
+
+            let id = token.propose(AdminAction::Migrate).unwrap();
+            set_caller(accounts.bob);
+            assert!(token.confirm(id).is_ok());
+            assert!(token.execute(id).is_ok());
+            assert_eq!(token.storage_version(), STORAGE_VERSION);
+        }
+
+        #[ink::test]
+        fn packed_root_fields_are_frozen() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new();
+            // A distinct value per packed field, so moving or retyping one shows up
+            token.total_supply = U256::from(1);
+            token.owner = H160::repeat_byte(2);
+            token.storage_version = 3;
+            token.wrapped = true;
+            token.custody = U256::from(5);
+            token.next_escrow_id = 6;
+            token.next_stream_id = 7;
+            token.total_staked = U256::from(8);
+            token.reward_per_token = U256::from(9);
+            token.reward_rate = U256::from(10);
+            token.last_reward_update = 11;
+            token.unbonding_period = 12;
+            token.dividend_per_share = U256::from(13);
+            token.dividend_reserve = U256::from(14);
+            token.pending_owner = Some(H160::repeat_byte(15));
+            token.multisig_signer_count = 16;
+            token.multisig_threshold = 17;
+            token.next_multisig_proposal_id = 18;
+            token.timelock_enabled = true;
+            token.timelock_min_delay = 20;
+            token.next_timelock_operation_id = 21;
+            token.governance_quorum = U256::from(22);
+            token.governance_voting_period = 23;
+            token.next_governance_proposal_id = 24;
+            token.holder_count = 25;
+            token.vault_assets = U256::from(26);
+            token.vault_total_shares = U256::from(27);
+            token.bridge_relayer_count = 28;
+            token.bridge_threshold = 29;
+
+            // Upgraded code must decode this cell before `migrate` can run. If this
+            // fails, put the new state in a `Lazy` or `Mapping` field instead.
+            let mut root = Vec::new();
+            ink::storage::traits::Storable::encode(&token, &mut root);
+
+            // Offset, encoding and name of every packed field, in storage order
+            let fields: [(usize, Vec<u8>, &str); 29] = [
+                (0, scale::Encode::encode(&U256::from(1)), "total_supply"),
+                (32, scale::Encode::encode(&H160::repeat_byte(2)), "owner"),
+                (52, scale::Encode::encode(&3u32), "storage_version"),
+                (56, scale::Encode::encode(&true), "wrapped"),
+                (57, scale::Encode::encode(&U256::from(5)), "custody"),
+                (89, scale::Encode::encode(&6u64), "next_escrow_id"),
+                (97, scale::Encode::encode(&7u64), "next_stream_id"),
+                (105, scale::Encode::encode(&U256::from(8)), "total_staked"),
+                (
+                    137,
+                    scale::Encode::encode(&U256::from(9)),
+                    "reward_per_token",
+                ),
+                (169, scale::Encode::encode(&U256::from(10)), "reward_rate"),
+                (201, scale::Encode::encode(&11u64), "last_reward_update"),
+                (209, scale::Encode::encode(&12u64), "unbonding_period"),
+                (
+                    217,
+                    scale::Encode::encode(&U256::from(13)),
+                    "dividend_per_share",
+                ),
+                (
+                    249,
+                    scale::Encode::encode(&U256::from(14)),
+                    "dividend_reserve",
+                ),
+                (
+                    281,
+                    scale::Encode::encode(&Some(H160::repeat_byte(15))),
+                    "pending_owner",
+                ),
+                (302, scale::Encode::encode(&16u32), "multisig_signer_count"),
+                (306, scale::Encode::encode(&17u32), "multisig_threshold"),
+                (
+                    310,
+                    scale::Encode::encode(&18u64),
+                    "next_multisig_proposal_id",
+                ),
+                (318, scale::Encode::encode(&true), "timelock_enabled"),
+                (319, scale::Encode::encode(&20u64), "timelock_min_delay"),
+                (
+                    327,
+                    scale::Encode::encode(&21u64),
+                    "next_timelock_operation_id",
+                ),
+                (
+                    335,
+                    scale::Encode::encode(&U256::from(22)),
+                    "governance_quorum",
+                ),
+                (
+                    367,
+                    scale::Encode::encode(&23u32),
+                    "governance_voting_period",
+                ),
+                (
+                    371,
+                    scale::Encode::encode(&24u64),
+                    "next_governance_proposal_id",
+                ),
+                (379, scale::Encode::encode(&25u32), "holder_count"),
+                (383, scale::Encode::encode(&U256::from(26)), "vault_assets"),
+                (
+                    415,
+                    scale::Encode::encode(&U256::from(27)),
+                    "vault_total_shares",
+                ),
+                (447, scale::Encode::encode(&28u32), "bridge_relayer_count"),
+                (451, scale::Encode::encode(&29u32), "bridge_threshold"),
+            ];
+            for (offset, encoded, name) in &fields {
+                assert_eq!(
+                    root[*offset..*offset + encoded.len()],
+                    encoded[..],
+                    "{name}"
+                );
+            }
+            assert_eq!(root.len(), 455);
+        }
+
+        #[ink::test]
+        fn account_data_and_metadata_round_trip() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = new_wrapped_token();
+            // A distinct value per field, so a dropped or reordered field shows up
+            let account = AccountData {
+                balance: U256::from(1),
+                nonce: 2,
+                frozen: true,
+                locked: U256::from(4),
+                dividend_per_share_paid: U256::from(5),
+                dividend_credit: U256::from(6),
+            };
+            token.set_account(accounts.bob, &account);
+            assert_eq!(token.accounts.get(accounts.bob), Some(account));
+            assert_eq!(token.balance_of(accounts.bob), U256::from(1));
+
+            // Empty records are removed instead of stored
+            token.set_account(accounts.bob, &AccountData::default());
+            assert!(!token.accounts.contains(accounts.bob));
+
+            assert_eq!(
+                token.metadata.get(),
+                Some(Metadata {
+                    name: String::from("Wrapped Native"),
+                    symbol: String::from("WNAT"),
+                    decimals: 18,
+                })
+            );
+        }
+
+        #[ink::test]
+        fn transfer_of_full_balance_removes_account() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(100));
+
+            assert!(
+                token
+                    .transfer(accounts.bob, U256::from(100), Vec::new())
+                    .is_ok()
+            );
+
+            assert!(!token.accounts.contains(accounts.alice));
+            assert_eq!(token.balance_of(accounts.alice), U256::from(0));
+            assert_eq!(token.balance_of(accounts.bob), U256::from(100));
+        }
+
+        #[ink::test]
+        fn burn_of_full_balance_removes_account() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(100));
+
+            assert!(token.burn(U256::from(100)).is_ok());
+
+            assert!(!token.accounts.contains(accounts.alice));
+            assert_eq!(token.balance_of(accounts.alice), U256::from(0));
+        }
+
+        #[ink::test]
+        fn spending_full_allowance_removes_entry() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            assert!(token.approve(accounts.bob, U256::from(100)).is_ok());
+
+            set_caller(accounts.bob);
+            assert!(
+                token
+                    .transfer_from(
+                        accounts.alice,
+                        accounts.charlie,
+                        U256::from(100),
+                        Vec::new()
+                    )
+                    .is_ok()
+            );
+
+            assert!(!token.allowances.contains((accounts.alice, accounts.bob)));
+            assert_eq!(
+                token.allowance(accounts.alice, accounts.bob),
+                U256::from(0)
+            );
+        }
+
+        #[ink::test]
+        fn decrease_allowance_to_zero_removes_entry() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            assert!(token.approve(accounts.bob, U256::from(100)).is_ok());
+            assert!(
+                token
+                    .decrease_allowance(accounts.bob, U256::from(100))
+                    .is_ok()
+            );
+
+            assert!(!token.allowances.contains((accounts.alice, accounts.bob)));
+            assert_eq!(
+                token.allowance(accounts.alice, accounts.bob),
+                U256::from(0)
+            );
+
+            // Approving zero clears the entry as well
+            assert!(token.approve(accounts.charlie, U256::from(100)).is_ok());
+            assert!(token.approve(accounts.charlie, U256::from(0)).is_ok());
+            assert!(!token.allowances.contains((accounts.alice, accounts.charlie)));
+        }
+
+        #[ink::test]
+        fn approve_with_expiry_works() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+            set_block_timestamp(100);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            assert!(
+                token
+                    .approve_with_expiry(accounts.bob, U256::from(200), 200)
+                    .is_ok()
+            );
+
+            assert_eq!(
+                token.allowance(accounts.alice, accounts.bob),
+                U256::from(200)
+            );
+            assert_eq!(token.allowance_expiry(accounts.alice, accounts.bob), Some(200));
+
+            set_block_timestamp(199);
+            set_caller(accounts.bob);
+            assert!(
+                token
+                    .transfer_from(accounts.alice, accounts.bob, U256::from(50), Vec::new())
+                    .is_ok()
+            );
+            assert_eq!(
+                token.allowance(accounts.alice, accounts.bob),
+                U256::from(150)
+            );
+        }
+
+        #[ink::test]
+        fn expired_allowance_reads_zero_and_cannot_be_spent() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+            set_block_timestamp(100);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            assert!(
+                token
+                    .approve_with_expiry(accounts.bob, U256::from(200), 200)
+                    .is_ok()
+            );
+
+            set_block_timestamp(200);
+            assert_eq!(
+                token.allowance(accounts.alice, accounts.bob),
+                U256::from(0)
+            );
+
+            set_caller(accounts.bob);
+            assert_eq!(
+                token.transfer_from(accounts.alice, accounts.bob, U256::from(50), Vec::new()),
+                Err(PSP22Error::AllowanceExpired)
+            );
+            assert_eq!(token.balance_of(accounts.alice), U256::from(1000));
+        }
+
+        #[ink::test]
+        fn approve_with_past_expiry_fails() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+            set_block_timestamp(100);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            assert_eq!(
+                token.approve_with_expiry(accounts.bob, U256::from(200), 100),
+                Err(PSP22Error::AllowanceExpired)
+            );
+        }
+
+        #[ink::test]
+        fn plain_approve_clears_expiry() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+            set_block_timestamp(100);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            assert!(
+                token
+                    .approve_with_expiry(accounts.bob, U256::from(200), 200)
+                    .is_ok()
+            );
+            assert!(token.approve(accounts.bob, U256::from(300)).is_ok());
+            assert_eq!(token.allowance_expiry(accounts.alice, accounts.bob), None);
+
+            set_block_timestamp(10_000);
+            assert_eq!(
+                token.allowance(accounts.alice, accounts.bob),
+                U256::from(300)
+            );
+        }
+
+        #[ink::test]
+        fn increase_expired_allowance_starts_from_zero() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+            set_block_timestamp(100);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            assert!(
+                token
+                    .approve_with_expiry(accounts.bob, U256::from(200), 200)
+                    .is_ok()
+            );
+
+            set_block_timestamp(300);
+            assert!(
+                token
+                    .increase_allowance(accounts.bob, U256::from(50))
+                    .is_ok()
+            );
+            assert_eq!(
+                token.allowance(accounts.alice, accounts.bob),
+                U256::from(50)
+            );
+            assert_eq!(token.allowance_expiry(accounts.alice, accounts.bob), None);
+        }
+
+        #[ink::test]
+        fn unlimited_allowance_is_not_decreased() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            assert!(token.approve(accounts.bob, U256::MAX).is_ok());
+
+            let events_before = test::recorded_events().into_iter().count();
+
+            set_caller(accounts.bob);
+            assert!(
+                token
+                    .transfer_from(accounts.alice, accounts.bob, U256::from(100), Vec::new())
+                    .is_ok()
+            );
+
+            assert_eq!(token.allowance(accounts.alice, accounts.bob), U256::MAX);
+            // Only the `Transfer` event, no `Approval`
+            assert_eq!(
+                test::recorded_events().into_iter().count(),
+                events_before + 1
+            );
+        }
+
+        #[ink::test]
+        fn allowance_below_max_is_decreased() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            let almost_unlimited = U256::MAX - U256::from(1);
+            assert!(token.approve(accounts.bob, almost_unlimited).is_ok());
+
+            let events_before = test::recorded_events().into_iter().count();
+
+            set_caller(accounts.bob);
+            assert!(
+                token
+                    .transfer_from(accounts.alice, accounts.bob, U256::from(100), Vec::new())
+                    .is_ok()
+            );
+
+            assert_eq!(
+                token.allowance(accounts.alice, accounts.bob),
+                almost_unlimited - U256::from(100)
+            );
+            // `Approval` and `Transfer` events
+            assert_eq!(
+                test::recorded_events().into_iter().count(),
+                events_before + 2
+            );
+        }
+
+        fn new_wrapped_token() -> PspCoin {
+            PspCoin::new_wrapped(String::from("Wrapped Native"), String::from("WNAT"), 18)
+        }
+
+        #[ink::test]
+        fn deposit_mints_transferred_value() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = new_wrapped_token();
+            assert!(token.is_wrapped());
+            assert_eq!(token.symbol(), Some(String::from("WNAT")));
+
+            test::set_value_transferred(U256::from(500));
+            assert!(token.deposit().is_ok());
+
+            assert_eq!(token.balance_of(accounts.alice), U256::from(500));
+            assert_eq!(token.total_supply(), U256::from(500));
+        }
+
+        #[ink::test]
+        fn withdraw_burns_and_returns_native() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = new_wrapped_token();
+            test::set_value_transferred(U256::from(500));
+            assert!(token.deposit().is_ok());
+
+            // The off-chain environment doesn't move the deposited value itself
+            let contract = ink::env::address();
+            test::set_account_balance(contract, U256::from(500));
+            let alice_native = test::get_account_balance(accounts.alice).unwrap();
+
+            test::set_value_transferred(U256::from(0));
+            assert!(token.withdraw(U256::from(200)).is_ok());
+
+            assert_eq!(token.balance_of(accounts.alice), U256::from(300));
+            assert_eq!(token.total_supply(), U256::from(300));
+            assert_eq!(
+                test::get_account_balance(contract).unwrap(),
+                token.total_supply()
+            );
+            assert_eq!(
+                test::get_account_balance(accounts.alice).unwrap(),
+                alice_native + U256::from(200)
+            );
+        }
+
+        #[ink::test]
+        fn withdraw_more_than_balance_fails() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = new_wrapped_token();
+            test::set_value_transferred(U256::from(100));
+            assert!(token.deposit().is_ok());
+
+            assert_eq!(
+                token.withdraw(U256::from(200)),
+                Err(PSP22Error::InsufficientBalance)
+            );
+        }
+
+        #[ink::test]
+        fn deposit_and_withdraw_reject_zero() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = new_wrapped_token();
+            let events_before = test::recorded_events().into_iter().count();
+
+            test::set_value_transferred(U256::from(0));
+            assert_eq!(token.deposit(), Err(PSP22Error::ZeroAmount));
+            assert_eq!(token.withdraw(U256::from(0)), Err(PSP22Error::ZeroAmount));
+            assert_eq!(test::recorded_events().into_iter().count(), events_before);
+        }
+
+        #[ink::test]
+        fn wrapped_mode_disables_free_supply_changes() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = new_wrapped_token();
+            assert_eq!(token.mint(U256::from(100)), Err(PSP22Error::WrappedMode));
+            assert_eq!(token.burn(U256::from(100)), Err(PSP22Error::WrappedMode));
+
+            let mut meme = PspCoin::new();
+            assert_eq!(meme.deposit(), Err(PSP22Error::NotWrapped));
+            assert_eq!(meme.withdraw(U256::from(1)), Err(PSP22Error::NotWrapped));
+        }
+
+        #[ink::test]
+        fn open_escrow_moves_tokens_into_custody() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+            set_block_timestamp(100);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            let id = token
+                .open_escrow(accounts.bob, accounts.charlie, U256::from(300), 1_000)
+                .unwrap();
+
+            assert_eq!(id, 0);
+            assert_eq!(token.balance_of(accounts.alice), U256::from(700));
+            assert_eq!(token.balance_of(ink::env::address()), U256::from(300));
+            assert_eq!(token.custody_balance(), U256::from(300));
+
+            let escrow = token.escrow(id).unwrap();
+            assert_eq!(escrow.payee, accounts.bob);
+            assert_eq!(escrow.status, EscrowStatus::Open);
+        }
+
+        #[ink::test]
+        fn release_by_payer_or_arbiter_pays_payee() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+            set_block_timestamp(100);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            let by_payer = token
+                .open_escrow(accounts.bob, accounts.charlie, U256::from(300), 1_000)
+                .unwrap();
+            let by_arbiter = token
+                .open_escrow(accounts.bob, accounts.charlie, U256::from(200), 1_000)
+                .unwrap();
+
+            // The payee can't release to themselves
+            set_caller(accounts.bob);
+            assert_eq!(token.release(by_payer), Err(PSP22Error::Unauthorized));
+
+            set_caller(accounts.alice);
+            assert!(token.release(by_payer).is_ok());
+            set_caller(accounts.charlie);
+            assert!(token.release(by_arbiter).is_ok());
+
+            assert_eq!(token.balance_of(accounts.bob), U256::from(500));
+            assert_eq!(token.custody_balance(), U256::from(0));
+            assert_eq!(
+                token.escrow(by_payer).unwrap().status,
+                EscrowStatus::Released
+            );
+            assert_eq!(token.release(by_arbiter), Err(PSP22Error::EscrowClosed));
+        }
+
+        #[ink::test]
+        fn refund_requires_deadline_or_arbiter() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+            set_block_timestamp(100);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            let after_deadline = token
+                .open_escrow(accounts.bob, accounts.charlie, U256::from(300), 1_000)
+                .unwrap();
+            let by_arbiter = token
+                .open_escrow(accounts.bob, accounts.charlie, U256::from(200), 1_000)
+                .unwrap();
+
+            assert_eq!(token.refund(after_deadline), Err(PSP22Error::EscrowNotExpired));
+
+            set_caller(accounts.charlie);
+            assert!(token.refund(by_arbiter).is_ok());
+
+            set_block_timestamp(1_000);
+            set_caller(accounts.alice);
+            assert!(token.refund(after_deadline).is_ok());
+
+            assert_eq!(token.balance_of(accounts.alice), U256::from(1000));
+            assert_eq!(token.balance_of(accounts.bob), U256::from(0));
+            assert_eq!(
+                token.escrow(after_deadline).unwrap().status,
+                EscrowStatus::Refunded
+            );
+            assert_eq!(token.release(after_deadline), Err(PSP22Error::EscrowClosed));
+        }
+
+        #[ink::test]
+        fn open_escrow_validates_input() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+            set_block_timestamp(100);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            assert_eq!(
+                token.open_escrow(accounts.bob, accounts.charlie, U256::from(0), 1_000),
+                Err(PSP22Error::ZeroAmount)
+            );
+            assert_eq!(
+                token.open_escrow(accounts.bob, accounts.charlie, U256::from(300), 100),
+                Err(PSP22Error::InvalidDeadline)
+            );
+            assert_eq!(
+                token.open_escrow(accounts.bob, accounts.charlie, U256::from(2000), 1_000),
+                Err(PSP22Error::InsufficientBalance)
+            );
+            assert_eq!(token.release(7), Err(PSP22Error::EscrowNotFound));
+        }
+
+        #[ink::test]
+        fn stream_balances_follow_elapsed_time() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+            set_block_timestamp(0);
+
+            let mut token = PspCoin::new_with_supply(U256::from(10_000));
+            let id = token
+                .create_stream(accounts.bob, U256::from(1_000), 100, 1_100)
+                .unwrap();
+
+            assert_eq!(token.balance_of(accounts.alice), U256::from(9_000));
+            assert_eq!(token.custody_balance(), U256::from(1_000));
+
+            // Nothing streamed before the start
+            assert_eq!(token.balance_of_stream(id, accounts.bob), U256::from(0));
+            assert_eq!(
+                token.balance_of_stream(id, accounts.alice),
+                U256::from(1_000)
+            );
+
+            set_block_timestamp(350);
+            assert_eq!(token.balance_of_stream(id, accounts.bob), U256::from(250));
+            assert_eq!(token.balance_of_stream(id, accounts.alice), U256::from(750));
+            assert_eq!(token.balance_of_stream(id, accounts.charlie), U256::from(0));
+
+            set_block_timestamp(5_000);
+            assert_eq!(
+                token.balance_of_stream(id, accounts.bob),
+                U256::from(1_000)
+            );
+            assert_eq!(token.balance_of_stream(id, accounts.alice), U256::from(0));
+        }
+
+        #[ink::test]
+        fn withdraw_from_stream_works() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+            set_block_timestamp(0);
+
+            let mut token = PspCoin::new_with_supply(U256::from(10_000));
+            let id = token
+                .create_stream(accounts.bob, U256::from(1_000), 0, 1_000)
+                .unwrap();
+
+            set_block_timestamp(400);
+            set_caller(accounts.bob);
+            assert_eq!(
+                token.withdraw_from_stream(id, U256::from(401)),
+                Err(PSP22Error::InsufficientBalance)
+            );
+            assert!(token.withdraw_from_stream(id, U256::from(300)).is_ok());
+            assert_eq!(token.balance_of(accounts.bob), U256::from(300));
+            assert_eq!(token.balance_of_stream(id, accounts.bob), U256::from(100));
+
+            // The stream is cleaned up once fully withdrawn
+            set_block_timestamp(1_000);
+            assert!(token.withdraw_from_stream(id, U256::from(700)).is_ok());
+            assert_eq!(token.balance_of(accounts.bob), U256::from(1_000));
+            assert_eq!(token.stream(id), None);
+            assert_eq!(token.custody_balance(), U256::from(0));
+        }
+
+        #[ink::test]
+        fn cancel_stream_splits_remaining_funds() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+            set_block_timestamp(0);
+
+            let mut token = PspCoin::new_with_supply(U256::from(10_000));
+            let id = token
+                .create_stream(accounts.bob, U256::from(1_000), 0, 1_000)
+                .unwrap();
+
+            set_block_timestamp(600);
+            set_caller(accounts.bob);
+            assert!(token.withdraw_from_stream(id, U256::from(100)).is_ok());
+
+            set_caller(accounts.charlie);
+            assert_eq!(token.cancel_stream(id), Err(PSP22Error::Unauthorized));
+
+            set_caller(accounts.alice);
+            assert!(token.cancel_stream(id).is_ok());
+
+            assert_eq!(token.balance_of(accounts.bob), U256::from(600));
+            assert_eq!(token.balance_of(accounts.alice), U256::from(9_400));
+            assert_eq!(token.custody_balance(), U256::from(0));
+            assert_eq!(token.stream(id), None);
+        }
+
+        #[ink::test]
+        fn create_stream_validates_schedule() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+            set_block_timestamp(500);
+
+            let mut token = PspCoin::new_with_supply(U256::from(10_000));
+            assert_eq!(
+                token.create_stream(accounts.bob, U256::from(1_000), 600, 600),
+                Err(PSP22Error::InvalidSchedule)
+            );
+            assert_eq!(
+                token.create_stream(accounts.bob, U256::from(1_000), 0, 500),
+                Err(PSP22Error::InvalidSchedule)
+            );
+            assert_eq!(
+                token.create_stream(accounts.bob, U256::from(0), 600, 700),
+                Err(PSP22Error::ZeroAmount)
+            );
+        }
+
+        #[ink::test]
+        fn stake_and_unstake_without_unbonding_works() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+
+            assert!(token.stake(U256::from(400)).is_ok());
+            assert_eq!(token.staked_balance_of(accounts.alice), U256::from(400));
+            assert_eq!(token.total_staked(), U256::from(400));
+            assert_eq!(token.balance_of(accounts.alice), U256::from(600));
+            assert_eq!(token.custody_balance(), U256::from(400));
+
+            assert_eq!(
+                token.unstake(U256::from(500)),
+                Err(PSP22Error::InsufficientBalance)
+            );
+            assert!(token.unstake(U256::from(400)).is_ok());
+            assert_eq!(token.staked_balance_of(accounts.alice), U256::from(0));
+            assert_eq!(token.balance_of(accounts.alice), U256::from(1000));
+            assert!(!token.stakes.contains(accounts.alice));
+        }
+
+        #[ink::test]
+        fn unstake_with_unbonding_period_locks_tokens() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+            set_block_timestamp(0);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            assert!(token.set_unbonding_period(100).is_ok());
+            assert!(token.stake(U256::from(400)).is_ok());
+
+            set_block_timestamp(50);
+            assert!(token.unstake(U256::from(300)).is_ok());
+            assert_eq!(
+                token.unbonding_of(accounts.alice),
+                (U256::from(300), 150)
+            );
+            assert_eq!(token.balance_of(accounts.alice), U256::from(600));
+
+            set_block_timestamp(149);
+            assert_eq!(token.withdraw_unbonded(), Err(PSP22Error::StillUnbonding));
+
+            set_block_timestamp(150);
+            assert!(token.withdraw_unbonded().is_ok());
+            assert_eq!(token.balance_of(accounts.alice), U256::from(900));
+            assert_eq!(token.unbonding_of(accounts.alice), (U256::from(0), 0));
+        }
+
+        #[ink::test]
+        fn deposited_rewards_are_shared_pro_rata() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(10_000));
+            assert!(
+                token
+                    .transfer(accounts.bob, U256::from(1_000), Vec::new())
+                    .is_ok()
+            );
+
+            assert!(token.stake(U256::from(3_000)).is_ok());
+            set_caller(accounts.bob);
+            assert!(token.stake(U256::from(1_000)).is_ok());
+
+            set_caller(accounts.charlie);
+            assert_eq!(
+                token.deposit_rewards(U256::from(400)),
+                Err(PSP22Error::InsufficientBalance)
+            );
+
+            set_caller(accounts.alice);
+            assert!(token.deposit_rewards(U256::from(400)).is_ok());
+            assert_eq!(token.pending_rewards(accounts.alice), U256::from(300));
+            assert_eq!(token.pending_rewards(accounts.bob), U256::from(100));
+
+            set_caller(accounts.bob);
+            assert!(token.claim_rewards().is_ok());
+            assert_eq!(token.balance_of(accounts.bob), U256::from(100));
+            assert_eq!(token.pending_rewards(accounts.bob), U256::from(0));
+        }
+
+        #[ink::test]
+        fn reward_emissions_are_minted_at_the_configured_rate() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+            set_block_timestamp(0);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1_000));
+            assert!(token.set_reward_rate(U256::from(2)).is_ok());
+            assert!(token.stake(U256::from(1_000)).is_ok());
+
+            set_block_timestamp(100);
+            assert_eq!(token.pending_rewards(accounts.alice), U256::from(200));
+            // Nothing is minted until rewards are accrued
+            assert_eq!(token.total_supply(), U256::from(1_000));
+
+            assert!(token.claim_rewards().is_ok());
+            assert_eq!(token.balance_of(accounts.alice), U256::from(200));
+            assert_eq!(token.total_supply(), U256::from(1_200));
+            assert_eq!(token.custody_balance(), U256::from(1_000));
+
+            set_caller(accounts.bob);
+            assert_eq!(token.set_reward_rate(U256::from(5)), Err(PSP22Error::Unauthorized));
+        }
+
+        #[ink::test]
+        fn distribute_splits_dividends_by_balance() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1_000));
+            assert!(
+                token
+                    .transfer(accounts.bob, U256::from(250), Vec::new())
+                    .is_ok()
+            );
+
+            set_caller(accounts.charlie);
+            test::set_value_transferred(U256::from(1_000));
+            assert!(token.distribute().is_ok());
+            test::set_value_transferred(U256::from(0));
+
+            assert_eq!(
+                token.withdrawable_dividend_of(accounts.alice),
+                U256::from(750)
+            );
+            assert_eq!(
+                token.withdrawable_dividend_of(accounts.bob),
+                U256::from(250)
+            );
+            assert_eq!(
+                token.withdrawable_dividend_of(accounts.charlie),
+                U256::from(0)
+            );
+            assert_eq!(token.dividend_reserve(), U256::from(1_000));
+        }
+
+        #[ink::test]
+        fn dividends_stay_with_the_holder_at_distribution_time() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1_000));
+
+            test::set_value_transferred(U256::from(1_000));
+            assert!(token.distribute().is_ok());
+            test::set_value_transferred(U256::from(0));
+
+            // Moving the tokens afterwards doesn't move the dividends
+            assert!(
+                token
+                    .transfer(accounts.bob, U256::from(1_000), Vec::new())
+                    .is_ok()
+            );
+            assert_eq!(
+                token.withdrawable_dividend_of(accounts.alice),
+                U256::from(1_000)
+            );
+            assert_eq!(token.withdrawable_dividend_of(accounts.bob), U256::from(0));
+
+            test::set_value_transferred(U256::from(500));
+            assert!(token.distribute().is_ok());
+            test::set_value_transferred(U256::from(0));
+
+            assert_eq!(
+                token.withdrawable_dividend_of(accounts.alice),
+                U256::from(1_000)
+            );
+            assert_eq!(
+                token.withdrawable_dividend_of(accounts.bob),
+                U256::from(500)
+            );
+        }
+
+        #[ink::test]
+        fn withdraw_dividend_sends_native_currency() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1_000));
+            test::set_value_transferred(U256::from(600));
+            assert!(token.distribute().is_ok());
+            test::set_value_transferred(U256::from(0));
+
+            // The off-chain environment doesn't move the distributed value itself
+            test::set_account_balance(ink::env::address(), U256::from(600));
+            let alice_native = test::get_account_balance(accounts.alice).unwrap();
+
+            assert!(token.withdraw_dividend().is_ok());
+            assert_eq!(
+                test::get_account_balance(accounts.alice).unwrap(),
+                alice_native + U256::from(600)
+            );
+            assert_eq!(token.withdrawable_dividend_of(accounts.alice), U256::from(0));
+            assert_eq!(token.dividend_reserve(), U256::from(0));
+        }
+
+        #[ink::test]
+        fn custody_is_excluded_from_dividends() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1_000));
+            assert!(token.stake(U256::from(500)).is_ok());
+
+            test::set_value_transferred(U256::from(500));
+            assert!(token.distribute().is_ok());
+            test::set_value_transferred(U256::from(0));
+
+            assert_eq!(
+                token.withdrawable_dividend_of(accounts.alice),
+                U256::from(500)
+            );
+            assert_eq!(
+                token.withdrawable_dividend_of(ink::env::address()),
+                U256::from(0)
+            );
+        }
+
+        #[ink::test]
+        fn locked_tokens_earn_no_dividends() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1_000));
+            assert!(
+                token
+                    .transfer(accounts.bob, U256::from(600), Vec::new())
+                    .is_ok()
+            );
+
+            // Bob locks everything, half staked and half in the vault
+            set_caller(accounts.bob);
+            assert!(token.stake(U256::from(300)).is_ok());
+            assert!(token.vault_deposit(U256::from(300), accounts.bob).is_ok());
+
+            set_caller(accounts.alice);
+            test::set_value_transferred(U256::from(400));
+            assert!(token.distribute().is_ok());
+            test::set_value_transferred(U256::from(0));
+
+            assert_eq!(
+                token.withdrawable_dividend_of(accounts.alice),
+                U256::from(400)
+            );
+            assert_eq!(token.withdrawable_dividend_of(accounts.bob), U256::from(0));
+        }
+
+        #[ink::test]
+        fn recover_own_tokens_leaves_custody_untouched() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1_000));
+            let contract = ink::env::address();
+
+            // 100 tokens sent by mistake, 200 staked
+            assert!(
+                token
+                    .transfer(contract, U256::from(100), Vec::new())
+                    .is_ok()
+            );
+            assert!(token.stake(U256::from(200)).is_ok());
+
+            assert_eq!(
+                token.recover_psp22(contract, accounts.bob, U256::from(101)),
+                Err(PSP22Error::InsufficientBalance)
+            );
+            assert!(
+                token
+                    .recover_psp22(contract, accounts.bob, U256::from(100))
+                    .is_ok()
+            );
+
+            assert_eq!(token.balance_of(accounts.bob), U256::from(100));
+            assert_eq!(token.balance_of(contract), U256::from(200));
+            assert_eq!(token.custody_balance(), U256::from(200));
+        }
+
+        #[ink::test]
+        fn recover_native_keeps_reserved_funds() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1_000));
+            test::set_value_transferred(U256::from(600));
+            assert!(token.distribute().is_ok());
+            test::set_value_transferred(U256::from(0));
+
+            test::set_account_balance(ink::env::address(), U256::from(1_000));
+            assert_eq!(token.reserved_native_balance(), U256::from(600));
+
+            assert_eq!(
+                token.recover_native(accounts.bob, U256::from(401)),
+                Err(PSP22Error::InsufficientBalance)
+            );
+            let bob_native = test::get_account_balance(accounts.bob).unwrap();
+            assert!(token.recover_native(accounts.bob, U256::from(400)).is_ok());
+            assert_eq!(
+                test::get_account_balance(accounts.bob).unwrap(),
+                bob_native + U256::from(400)
+            );
+        }
+
+        #[ink::test]
+        fn recovery_is_owner_only() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1_000));
+
+            set_caller(accounts.bob);
+            assert_eq!(
+                token.recover_psp22(ink::env::address(), accounts.bob, U256::from(1)),
+                Err(PSP22Error::Unauthorized)
+            );
+            assert_eq!(
+                token.recover_native(accounts.bob, U256::from(1)),
+                Err(PSP22Error::Unauthorized)
+            );
+        }
+
+        #[ink::test]
+        fn ownership_transfer_requires_acceptance() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new();
+            assert!(token.transfer_ownership(accounts.bob).is_ok());
+            assert_eq!(token.owner(), accounts.alice);
+            assert_eq!(token.pending_owner(), Some(accounts.bob));
+
+            set_caller(accounts.charlie);
+            assert_eq!(token.accept_ownership(), Err(PSP22Error::Unauthorized));
+
+            set_caller(accounts.bob);
+            assert!(token.accept_ownership().is_ok());
+            assert_eq!(token.owner(), accounts.bob);
+            assert_eq!(token.pending_owner(), None);
+
+            // The previous owner lost its rights
+            set_caller(accounts.alice);
+            assert_eq!(token.transfer_ownership(accounts.alice), Err(PSP22Error::Unauthorized));
+        }
+
+        #[ink::test]
+        fn cancel_ownership_transfer_works() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new();
+            assert_eq!(token.cancel_ownership_transfer(), Err(PSP22Error::NoPendingOwner));
+
+            assert!(token.transfer_ownership(accounts.bob).is_ok());
+            assert!(token.cancel_ownership_transfer().is_ok());
+            assert_eq!(token.pending_owner(), None);
+
+            set_caller(accounts.bob);
+            assert_eq!(token.accept_ownership(), Err(PSP22Error::Unauthorized));
+            assert_eq!(token.owner(), accounts.alice);
+        }
+
+        /// Alice deploys and hands control to a 2-of-3 multisig of Alice, Bob and Charlie
+        fn new_multisig_token() -> PspCoin {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new();
+            assert!(
+                token
+                    .enable_multisig(
+                        ink::prelude::vec![accounts.alice, accounts.bob, accounts.charlie],
+                        2
+                    )
+                    .is_ok()
+            );
+            token
+        }
+
+        #[ink::test]
+        fn mint_is_owner_only() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new();
+
+            set_caller(accounts.bob);
+            assert_eq!(token.mint(U256::from(100)), Err(PSP22Error::Unauthorized));
+            assert_eq!(token.total_supply(), U256::from(0));
+
+            // Under the timelock even the owner has to schedule minting
+            set_caller(accounts.alice);
+            assert!(token.enable_timelock(1_000).is_ok());
+            assert_eq!(token.mint(U256::from(100)), Err(PSP22Error::Timelocked));
+            set_caller(accounts.bob);
+            assert_eq!(token.mint(U256::from(100)), Err(PSP22Error::Unauthorized));
+            assert_eq!(token.total_supply(), U256::from(0));
+        }
+
+        #[ink::test]
+        fn enable_multisig_takes_over_privileged_actions() {
+            let accounts = default_accounts();
+            let mut token = new_multisig_token();
+
+            assert!(token.is_multisig_enabled());
+            assert_eq!(token.owner(), ink::env::address());
+            assert_eq!(token.multisig_threshold(), (2, 3));
+
+            // The former owner can't act alone anymore
+            assert_eq!(token.mint(U256::from(100)), Err(PSP22Error::Unauthorized));
+
+            set_caller(accounts.django);
+            assert_eq!(
+                token.propose(AdminAction::Mint {
+                    to: accounts.django,
+                    value: U256::from(100)
+                }),
+                Err(PSP22Error::Unauthorized)
+            );
+        }
+
+        #[ink::test]
+        fn multisig_executes_after_threshold_confirmations() {
+            let accounts = default_accounts();
+            let mut token = new_multisig_token();
+
+            let id = token
+                .propose(AdminAction::Mint {
+                    to: accounts.django,
+                    value: U256::from(100),
+                })
+                .unwrap();
+
+            assert_eq!(token.execute(id), Err(PSP22Error::NotEnoughConfirmations));
+            assert_eq!(token.confirm(id), Err(PSP22Error::AlreadyConfirmed));
+
+            set_caller(accounts.bob);
+            assert!(token.confirm(id).is_ok());
+            assert!(token.execute(id).is_ok());
+
+            assert_eq!(token.balance_of(accounts.django), U256::from(100));
+            assert_eq!(token.multisig_proposal(id), None);
+            assert_eq!(token.execute(id), Err(PSP22Error::ProposalNotFound));
+        }
+
+        #[ink::test]
+        fn revoked_confirmations_do_not_count() {
+            let accounts = default_accounts();
+            let mut token = new_multisig_token();
+
+            let id = token
+                .propose(AdminAction::SetUnbondingPeriod {
+                    unbonding_period: 100,
+                })
+                .unwrap();
+
+            set_caller(accounts.bob);
+            assert_eq!(token.revoke_confirmation(id), Err(PSP22Error::NotConfirmed));
+            assert!(token.confirm(id).is_ok());
+            assert!(token.revoke_confirmation(id).is_ok());
+
+            assert_eq!(token.execute(id), Err(PSP22Error::NotEnoughConfirmations));
+            assert_eq!(token.unbonding_period(), 0);
+        }
+
+        #[ink::test]
+        fn signer_changes_require_multisig_approval() {
+            let accounts = default_accounts();
+            let mut token = new_multisig_token();
+
+            let add = token
+                .propose(AdminAction::AddSigner {
+                    signer: accounts.django,
+                })
+                .unwrap();
+            let raise = token
+                .propose(AdminAction::SetThreshold { threshold: 4 })
+                .unwrap();
+
+            set_caller(accounts.bob);
+            assert!(token.confirm(add).is_ok());
+            assert!(token.confirm(raise).is_ok());
+
+            // The threshold can't exceed the signer count
+            assert_eq!(token.execute(raise), Err(PSP22Error::InvalidThreshold));
+
+            assert!(token.execute(add).is_ok());
+            assert!(token.is_multisig_signer(accounts.django));
+
+            let raise = token
+                .propose(AdminAction::SetThreshold { threshold: 4 })
+                .unwrap();
+            set_caller(accounts.charlie);
+            assert!(token.confirm(raise).is_ok());
+            assert!(token.execute(raise).is_ok());
+            assert_eq!(token.multisig_threshold(), (4, 4));
+
+            // Removing a signer would make the threshold unreachable
+            let remove = token
+                .propose(AdminAction::RemoveSigner {
+                    signer: accounts.django,
+                })
+                .unwrap();
+            for signer in [accounts.alice, accounts.bob, accounts.django] {
+                set_caller(signer);
+                assert!(token.confirm(remove).is_ok());
+            }
+            assert_eq!(token.execute(remove), Err(PSP22Error::InvalidThreshold));
+        }
+
+        #[ink::test]
+        fn timelock_rejects_direct_admin_calls() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new();
+            assert!(token.enable_timelock(1_000).is_ok());
+            assert!(token.is_timelock_enabled());
+
+            assert_eq!(token.mint(U256::from(100)), Err(PSP22Error::Timelocked));
+            assert_eq!(token.set_unbonding_period(100), Err(PSP22Error::Timelocked));
+            assert_eq!(token.enable_timelock(0), Err(PSP22Error::Timelocked));
+        }
+
+        #[ink::test]
+        fn scheduled_action_waits_for_eta() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+            set_block_timestamp(1_000);
+
+            let mut token = PspCoin::new();
+            assert!(token.enable_timelock(1_000).is_ok());
+
+            let action = AdminAction::Mint {
+                to: accounts.bob,
+                value: U256::from(100),
+            };
+            assert_eq!(token.schedule(action.clone(), 1_999), Err(PSP22Error::DelayTooShort));
+
+            set_caller(accounts.bob);
+            assert_eq!(token.schedule(action.clone(), 2_000), Err(PSP22Error::Unauthorized));
+
+            set_caller(accounts.alice);
+            let id = token.schedule(action.clone(), 2_000).unwrap();
+            assert_eq!(
+                token.scheduled_operation(id),
+                Some(TimelockOperation { action, eta: 2_000 })
+            );
+
+            // Anyone can execute, but only once the eta has passed
+            set_caller(accounts.charlie);
+            set_block_timestamp(1_999);
+            assert_eq!(token.execute_scheduled(id), Err(PSP22Error::OperationNotReady));
+
+            set_block_timestamp(2_000);
+            assert!(token.execute_scheduled(id).is_ok());
+            assert_eq!(token.balance_of(accounts.bob), U256::from(100));
+            assert_eq!(token.scheduled_operation(id), None);
+            assert_eq!(token.execute_scheduled(id), Err(PSP22Error::OperationNotFound));
+        }
+
+        #[ink::test]
+        fn cancel_scheduled_is_immediate() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            set_block_timestamp(1_000);
+
+            let mut token = PspCoin::new();
+            assert!(token.enable_timelock(1_000).is_ok());
+
+            let id = token
+                .schedule(AdminAction::SetTimelockDelay { min_delay: 0 }, 2_000)
+                .unwrap();
+
+            set_caller(accounts.bob);
+            assert_eq!(token.cancel_scheduled(id), Err(PSP22Error::Unauthorized));
+
+            set_caller(accounts.alice);
+            assert!(token.cancel_scheduled(id).is_ok());
+
+            set_block_timestamp(2_000);
+            assert_eq!(token.execute_scheduled(id), Err(PSP22Error::OperationNotFound));
+            assert_eq!(token.timelock_min_delay(), 1_000);
+        }
+
+        #[ink::test]
+        fn pending_operations_lists_scheduled_ids() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+            set_block_timestamp(1_000);
+
+            let mut token = PspCoin::new();
+            assert!(token.enable_timelock(1_000).is_ok());
+            assert_eq!(token.pending_operation_count(), 0);
+
+            let ids: Vec<u64> = (1..=3)
+                .map(|unbonding_period| {
+                    let action = AdminAction::SetUnbondingPeriod { unbonding_period };
+                    token.schedule(action, 2_000).unwrap()
+                })
+                .collect();
+            assert_eq!(token.pending_operation_count(), 3);
+            let listed: Vec<u64> = token
+                .pending_operations(0, 10)
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect();
+            assert_eq!(listed, ids);
+
+            // Canceling the first moves the last into its slot
+            assert!(token.cancel_scheduled(ids[0]).is_ok());
+            set_block_timestamp(2_000);
+            assert!(token.execute_scheduled(ids[1]).is_ok());
+            assert_eq!(token.pending_operation_count(), 1);
+            assert_eq!(
+                token.pending_operations(0, 10),
+                ink::prelude::vec![(
+                    ids[2],
+                    TimelockOperation {
+                        action: AdminAction::SetUnbondingPeriod {
+                            unbonding_period: 3,
+                        },
+                        eta: 2_000,
+                    }
+                )]
+            );
+            assert_eq!(token.pending_operations(1, 10), Vec::new());
+        }
+
+        #[ink::test]
+        fn multisig_execute_schedules_under_timelock() {
+            let accounts = default_accounts();
+            set_block_timestamp(1_000);
+            let mut token = new_multisig_token();
+
+            let enable = token
+                .propose(AdminAction::EnableTimelock { min_delay: 1_000 })
+                .unwrap();
+            set_caller(accounts.bob);
+            assert!(token.confirm(enable).is_ok());
+            assert!(token.execute(enable).is_ok());
+            assert!(token.is_timelock_enabled());
+
+            let mint = token
+                .propose(AdminAction::Mint {
+                    to: accounts.django,
+                    value: U256::from(100),
+                })
+                .unwrap();
+            set_caller(accounts.charlie);
+            assert!(token.confirm(mint).is_ok());
+            assert!(token.execute(mint).is_ok());
+
+            // The approved mint is queued rather than performed
+            assert_eq!(token.balance_of(accounts.django), U256::from(0));
+            assert!(token.scheduled_operation(0).is_some());
+
+            set_block_timestamp(2_000);
+            assert!(token.execute_scheduled(0).is_ok());
+            assert_eq!(token.balance_of(accounts.django), U256::from(100));
+        }
+
+        #[ink::test]
+        fn balance_of_at_tracks_history() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            assert!(token.set_governance_settings(U256::from(500), 10).is_ok());
+            let start = ink::env::block_number::<ink::env::DefaultEnvironment>();
+
+            advance_blocks(1);
+            assert!(token.transfer(accounts.bob, U256::from(100), Vec::new()).is_ok());
+            assert!(token.transfer(accounts.bob, U256::from(100), Vec::new()).is_ok());
+            advance_blocks(2);
+            assert!(token.transfer(accounts.bob, U256::from(300), Vec::new()).is_ok());
+
+            assert_eq!(token.balance_of_at(accounts.alice, start), U256::from(1000));
+            assert_eq!(token.balance_of_at(accounts.bob, start), U256::from(0));
+            assert_eq!(token.balance_of_at(accounts.bob, start + 1), U256::from(200));
+            assert_eq!(token.balance_of_at(accounts.bob, start + 2), U256::from(200));
+            assert_eq!(token.balance_of_at(accounts.bob, start + 3), U256::from(500));
+            assert_eq!(token.balance_of_at(accounts.alice, start + 3), U256::from(500));
+        }
+
+        #[ink::test]
+        fn balance_history_starts_with_governance() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            assert!(token.transfer(accounts.bob, U256::from(100), Vec::new()).is_ok());
+            assert_eq!(token.balance_checkpoint_counts.get(accounts.alice), None);
+            assert_eq!(token.balance_checkpoint_counts.get(accounts.bob), None);
+
+            advance_blocks(1);
+            assert!(token.set_governance_settings(U256::from(500), 10).is_ok());
+            let start = ink::env::block_number::<ink::env::DefaultEnvironment>();
+            assert_eq!(token.balance_of_at(accounts.alice, start - 1), U256::from(0));
+            assert_eq!(token.balance_of_at(accounts.alice, start), U256::from(900));
+
+            // The first move keeps the balance held since history started
+            advance_blocks(2);
+            assert!(token.transfer(accounts.bob, U256::from(400), Vec::new()).is_ok());
+            assert_eq!(token.balance_of_at(accounts.alice, start + 1), U256::from(900));
+            assert_eq!(token.balance_of_at(accounts.alice, start + 2), U256::from(500));
+            assert_eq!(token.balance_of_at(accounts.bob, start), U256::from(100));
+            assert_eq!(token.balance_of_at(accounts.bob, start + 2), U256::from(500));
+        }
+
+        #[ink::test]
+        fn governance_requires_settings() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            advance_blocks(1);
+
+            let actions = ink::prelude::vec![AdminAction::SetUnbondingPeriod {
+                unbonding_period: 100,
+            }];
+            assert_eq!(
+                token.create_proposal(H256::zero(), actions.clone()),
+                Err(PSP22Error::GovernanceDisabled)
+            );
+            assert_eq!(
+                token.set_governance_settings(U256::from(500), 0),
+                Err(PSP22Error::InvalidVotingPeriod)
+            );
+            assert_eq!(
+                token.set_governance_settings(U256::from(0), 10),
+                Err(PSP22Error::InvalidParameter)
+            );
+
+            assert!(token.set_governance_settings(U256::from(500), 10).is_ok());
+            assert_eq!(
+                token.create_proposal(H256::zero(), Vec::new()),
+                Err(PSP22Error::EmptyProposal)
+            );
+
+            set_caller(accounts.bob);
+            assert_eq!(
+                token.create_proposal(H256::zero(), actions),
+                Err(PSP22Error::NoVotingPower)
+            );
+        }
+
+        #[ink::test]
+        fn governance_proposal_lifecycle() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            assert!(token.transfer(accounts.bob, U256::from(400), Vec::new()).is_ok());
+            assert!(token.set_governance_settings(U256::from(500), 10).is_ok());
+            advance_blocks(1);
+
+            let id = token
+                .create_proposal(
+                    H256::repeat_byte(1),
+                    ink::prelude::vec![AdminAction::Mint {
+                        to: accounts.charlie,
+                        value: U256::from(50),
+                    }],
+                )
+                .unwrap();
+            assert_eq!(token.proposal_state(id), Some(ProposalState::Active));
+
+            assert!(token.cast_vote(id, true).is_ok());
+            assert_eq!(token.cast_vote(id, true), Err(PSP22Error::AlreadyVoted));
+
+            // Tokens moved after the snapshot don't carry votes with them
+            set_caller(accounts.bob);
+            assert!(token.transfer(accounts.charlie, U256::from(400), Vec::new()).is_ok());
+            assert!(token.cast_vote(id, false).is_ok());
+            set_caller(accounts.charlie);
+            assert_eq!(token.cast_vote(id, true), Err(PSP22Error::NoVotingPower));
+
+            assert_eq!(token.vote_of(id, accounts.alice), Some(true));
+            assert_eq!(token.vote_of(id, accounts.bob), Some(false));
+            let proposal = token.governance_proposal(id).unwrap();
+            assert_eq!(proposal.for_votes, U256::from(600));
+            assert_eq!(proposal.against_votes, U256::from(400));
+
+            assert_eq!(token.queue_proposal(id), Err(PSP22Error::ProposalNotSucceeded));
+
+            advance_blocks(10);
+            assert_eq!(token.proposal_state(id), Some(ProposalState::Succeeded));
+            assert_eq!(token.cast_vote(id, true), Err(PSP22Error::VotingClosed));
+
+            assert!(token.queue_proposal(id).is_ok());
+            assert_eq!(token.proposal_state(id), Some(ProposalState::Queued));
+            assert!(token.execute_proposal(id).is_ok());
+            assert_eq!(token.proposal_state(id), Some(ProposalState::Executed));
+            assert_eq!(token.balance_of(accounts.charlie), U256::from(450));
+            assert_eq!(token.execute_proposal(id), Err(PSP22Error::ProposalNotQueued));
+        }
+
+        #[ink::test]
+        fn governance_proposal_without_quorum_is_defeated() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            assert!(token.transfer(accounts.bob, U256::from(600), Vec::new()).is_ok());
+            assert!(token.set_governance_settings(U256::from(500), 10).is_ok());
+            advance_blocks(1);
+
+            let id = token
+                .create_proposal(
+                    H256::zero(),
+                    ink::prelude::vec![AdminAction::SetUnbondingPeriod {
+                        unbonding_period: 100,
+                    }],
+                )
+                .unwrap();
+            assert!(token.cast_vote(id, true).is_ok());
+
+            advance_blocks(11);
+            assert_eq!(token.proposal_state(id), Some(ProposalState::Defeated));
+            assert_eq!(token.queue_proposal(id), Err(PSP22Error::ProposalNotSucceeded));
+        }
+
+        #[ink::test]
+        fn governance_proposal_threshold() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            assert!(
+                token
+                    .transfer(accounts.bob, U256::from(100), Vec::new())
+                    .is_ok()
+            );
+            assert!(token.set_governance_settings(U256::from(500), 10).is_ok());
+            assert!(token.set_proposal_threshold(U256::from(200)).is_ok());
+            assert_eq!(token.proposal_threshold(), U256::from(200));
+            advance_blocks(1);
+
+            let actions = ink::prelude::vec![AdminAction::SetUnbondingPeriod {
+                unbonding_period: 100,
+            }];
+            set_caller(accounts.bob);
+            assert_eq!(
+                token.create_proposal(H256::zero(), actions.clone()),
+                Err(PSP22Error::BelowProposalThreshold)
+            );
+            assert_eq!(
+                token.set_proposal_threshold(U256::from(0)),
+                Err(PSP22Error::Unauthorized)
+            );
+
+            set_caller(accounts.alice);
+            assert!(token.create_proposal(H256::zero(), actions).is_ok());
+        }
+
+        #[ink::test]
+        fn governance_execution_waits_for_current_delay() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            assert!(token.set_governance_settings(U256::from(500), 10).is_ok());
+            advance_blocks(1);
+
+            let id = token
+                .create_proposal(
+                    H256::zero(),
+                    ink::prelude::vec![AdminAction::SetUnbondingPeriod {
+                        unbonding_period: 100,
+                    }],
+                )
+                .unwrap();
+            assert!(token.cast_vote(id, true).is_ok());
+            advance_blocks(10);
+            set_block_timestamp(1_000);
+            assert!(token.queue_proposal(id).is_ok());
+
+            // Enabling the timelock after queuing still holds the proposal back
+            assert!(token.enable_timelock(500).is_ok());
+            assert_eq!(
+                token.execute_proposal(id),
+                Err(PSP22Error::OperationNotReady)
+            );
+
+            set_block_timestamp(1_499);
+            assert_eq!(
+                token.execute_proposal(id),
+                Err(PSP22Error::OperationNotReady)
+            );
+            set_block_timestamp(1_500);
+            assert!(token.execute_proposal(id).is_ok());
+            assert_eq!(token.unbonding_period(), 100);
+        }
+
+        #[ink::test]
+        fn holder_count_follows_balances() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            assert_eq!(token.holder_count(), 1);
+
+            assert!(token.transfer(accounts.bob, U256::from(100), Vec::new()).is_ok());
+            assert!(token.transfer(accounts.bob, U256::from(100), Vec::new()).is_ok());
+            assert_eq!(token.holder_count(), 2);
+
+            assert!(token.mint(U256::from(50)).is_ok());
+            assert_eq!(token.holder_count(), 2);
+
+            set_caller(accounts.bob);
+            assert!(token.transfer(accounts.charlie, U256::from(150), Vec::new()).is_ok());
+            assert!(token.burn(U256::from(50)).is_ok());
+            assert_eq!(token.holder_count(), 2);
+            assert_eq!(
+                token.holders(0, 10),
+                ink::prelude::vec![
+                    (accounts.alice, U256::from(850)),
+                    (accounts.charlie, U256::from(150))
+                ]
+            );
+        }
+
+        #[ink::test]
+        fn holders_paginates() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            for to in [accounts.bob, accounts.charlie, accounts.django] {
+                assert!(token.transfer(to, U256::from(100), Vec::new()).is_ok());
+            }
+
+            assert_eq!(
+                token.holders(1, 2),
+                ink::prelude::vec![
+                    (accounts.bob, U256::from(100)),
+                    (accounts.charlie, U256::from(100))
+                ]
+            );
+            assert_eq!(
+                token.holders(3, 10),
+                ink::prelude::vec![(accounts.django, U256::from(100))]
+            );
+            assert!(token.holders(4, 10).is_empty());
+            assert!(token.holders(u32::MAX, u32::MAX).is_empty());
+        }
+
+        #[ink::test]
+        fn custody_is_not_a_holder() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+            set_block_timestamp(100);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            let id = token
+                .open_escrow(accounts.bob, accounts.charlie, U256::from(300), 1_000)
+                .unwrap();
+            assert_eq!(token.balance_of(ink::env::address()), U256::from(300));
+            assert_eq!(token.holder_count(), 1);
+            assert_eq!(
+                token.holders(0, 10),
+                ink::prelude::vec![(accounts.alice, U256::from(700))]
+            );
+
+            assert!(token.release(id).is_ok());
+            assert_eq!(token.holder_count(), 2);
+            assert_eq!(
+                token.holders(0, 10),
+                ink::prelude::vec![
+                    (accounts.alice, U256::from(700)),
+                    (accounts.bob, U256::from(300))
+                ]
+            );
+        }
+
+        #[ink::test]
+        fn error_codes_match_encoding() {
+            for (error, code) in [
+                (PSP22Error::InsufficientBalance, 0),
+                (PSP22Error::Overflow, 2),
+                (PSP22Error::Custom(String::from("Error")), 3),
+                (PSP22Error::AllowanceExpired, 4),
+                (PSP22Error::Unauthorized, 5),
+                (PSP22Error::ProposalNotQueued, 49),
+                (PSP22Error::NotRelayer, 60),
+                (PSP22Error::ChainIdNotSet, 61),
+                (PSP22Error::InvalidParameter, 62),
+                (PSP22Error::MigrationRequired, 63),
+                (PSP22Error::BelowProposalThreshold, 64),
+            ] {
+                assert_eq!(error.code(), code);
+                assert_eq!(scale::Encode::encode(&error)[0], code);
+            }
+        }
+
+        #[ink::test]
+        fn error_codes_follow_declaration_order() {
+            use ink::scale_info::{TypeDef, TypeInfo};
+
+            // Catches a variant inserted or pinned out of order instead of appended
+            let TypeDef::Variant(errors) = PSP22Error::type_info().type_def else {
+                panic!("PSP22Error is an enum");
+            };
+            assert_eq!(errors.variants.len(), 65);
+            for (position, variant) in errors.variants.iter().enumerate() {
+                assert_eq!(usize::from(variant.index), position, "{}", variant.name);
+            }
+        }
+
+        #[ink::test]
+        fn to_legacy_keeps_standard_variants() {
+            assert_eq!(
+                PSP22Error::InsufficientAllowance.to_legacy(),
+                PSP22Error::InsufficientAllowance
+            );
+            assert_eq!(
+                PSP22Error::Unauthorized.to_legacy(),
+                PSP22Error::Custom(String::from("Unauthorized"))
+            );
+            assert_eq!(
+                PSP22Error::AllowanceExpired.to_legacy(),
+                PSP22Error::Custom(String::from("AllowanceExpired"))
+            );
+        }
+
+        #[ink::test]
+        fn zero_address_is_rejected() {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            let zero = H160::zero();
+
+            assert_eq!(
+                token.transfer(zero, U256::from(100), Vec::new()),
+                Err(PSP22Error::ZeroAddress)
+            );
+            assert_eq!(token.approve(zero, U256::from(100)), Err(PSP22Error::ZeroAddress));
+            assert_eq!(
+                token.increase_allowance(zero, U256::from(100)),
+                Err(PSP22Error::ZeroAddress)
+            );
+            assert_eq!(token.mint_to(zero, U256::from(100)), Err(PSP22Error::ZeroAddress));
+
+            assert!(token.approve(accounts.bob, U256::from(100)).is_ok());
+            set_caller(accounts.bob);
+            assert_eq!(
+                token.transfer_from(accounts.alice, zero, U256::from(100), Vec::new()),
+                Err(PSP22Error::ZeroAddress)
+            );
+
+            assert_eq!(token.balance_of(zero), U256::from(0));
+            assert_eq!(token.total_supply(), U256::from(1000));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "ZeroAddress")]
+        fn constructor_rejects_zero_address() {
+            set_caller(H160::zero());
+            PspCoin::new_with_supply(U256::from(1000));
+        }
+
+        const AUTHORIZATION_KEY: [u8; 32] = [9u8; 32];
+
+        /// Alice deploys and funds the account behind `AUTHORIZATION_KEY` with 1000 tokens
+        fn new_authorization_token() -> (PspCoin, H160) {
+            let accounts = default_accounts();
+            set_caller(accounts.alice);
+            set_block_timestamp(1_000);
+
+            let mut token = PspCoin::new_with_chain_id(U256::from(1000), CHAIN_ID);
+            let from = address_of(AUTHORIZATION_KEY);
+            assert!(token.transfer(from, U256::from(1000), Vec::new()).is_ok());
+            (token, from)
+        }
+
+        #[ink::test]
+        fn authorization_typehashes_match_eip_3009() {
+            assert_eq!(
+                PspCoin::keccak256(TRANSFER_WITH_AUTHORIZATION_TYPE.as_bytes()),
+                [
+                    0x7c, 0x7c, 0x6c, 0xdb, 0x67, 0xa1, 0x87, 0x43, 0xf4, 0x9e, 0xc6, 0xfa, 0x9b,
+                    0x35, 0xf5, 0x0d, 0x52, 0xed, 0x05, 0xcb, 0xed, 0x4c, 0xc5, 0x92, 0xe1, 0x3b,
+                    0x44, 0x50, 0x1c, 0x1a, 0x22, 0x67
+                ]
+            );
+            assert_eq!(
+                PspCoin::keccak256(EIP712_DOMAIN_TYPE.as_bytes()),
+                [
+                    0x8b, 0x73, 0xc3, 0xc6, 0x9b, 0xb8, 0xfe, 0x3d, 0x51, 0x2e, 0xcc, 0x4c, 0xf7,
+                    0x59, 0xcc, 0x79, 0x23, 0x9f, 0x7b, 0x17, 0x9b, 0x0f, 0xfa, 0xca, 0xa9, 0xa7,
+                    0x5d, 0x52, 0x2b, 0x39, 0x40, 0x0f
+                ]
+            );
+        }
+
+        #[ink::test]
+        fn authorization_is_bound_to_chain_id() {
+            let accounts = default_accounts();
+            let (mut token, from) = new_authorization_token();
+            let nonce = H256::repeat_byte(4);
+
+            let digest = token.authorization_digest(
+                TRANSFER_WITH_AUTHORIZATION_TYPE,
+                from,
+                accounts.bob,
+                U256::from(100),
+                0,
+                2_000,
+                nonce,
+            );
+            let (v, r, s) = split_signature(sign(AUTHORIZATION_KEY, digest));
+
+            // The same contract address deployed on another chain
+            token.chain_id.set(&(CHAIN_ID + 1));
+            assert_eq!(
+                token.transfer_with_authorization(
+                    from,
+                    accounts.bob,
+                    U256::from(100),
+                    0,
+                    2_000,
+                    nonce,
+                    v,
+                    r,
+                    s
+                ),
+                Err(PSP22Error::InvalidSignature)
+            );
+        }
+
+        #[ink::test]
+        fn transfer_with_authorization_works() {
+            let accounts = default_accounts();
+            let (mut token, from) = new_authorization_token();
+            let nonce = H256::repeat_byte(1);
+
+            let digest = token.authorization_digest(
+                TRANSFER_WITH_AUTHORIZATION_TYPE,
+                from,
+                accounts.bob,
+                U256::from(100),
+                0,
+                2_000,
+                nonce,
+            );
+            let (v, r, s) = split_signature(sign(AUTHORIZATION_KEY, digest));
+
+            // A tampered value doesn't match the signature
+            set_caller(accounts.charlie);
+            assert_eq!(
+                token.transfer_with_authorization(
+                    from,
+                    accounts.bob,
+                    U256::from(200),
+                    0,
+                    2_000,
+                    nonce,
+                    v,
+                    r,
+                    s
+                ),
+                Err(PSP22Error::InvalidSignature)
+            );
+
+            assert!(
+                token
+                    .transfer_with_authorization(
+                        from,
+                        accounts.bob,
+                        U256::from(100),
+                        0,
+                        2_000,
+                        nonce,
+                        v,
+                        r,
+                        s
+                    )
+                    .is_ok()
+            );
+            assert_eq!(token.balance_of(accounts.bob), U256::from(100));
+            assert_eq!(token.balance_of(from), U256::from(900));
+            assert!(token.authorization_state(from, nonce));
+
+            assert_eq!(
+                token.transfer_with_authorization(
+                    from,
+                    accounts.bob,
+                    U256::from(100),
+                    0,
+                    2_000,
+                    nonce,
+                    v,
+                    r,
+                    s
+                ),
+                Err(PSP22Error::AuthorizationAlreadyUsed)
+            );
+        }
+
+        #[ink::test]
+        fn transfer_with_authorization_respects_validity_window() {
+            let accounts = default_accounts();
+            let (mut token, from) = new_authorization_token();
+            let nonce = H256::repeat_byte(2);
+
+            let digest = token.authorization_digest(
+                TRANSFER_WITH_AUTHORIZATION_TYPE,
+                from,
+                accounts.bob,
+                U256::from(100),
+                1_000,
+                2_000,
+                nonce,
+            );
+            let (v, r, s) = split_signature(sign(AUTHORIZATION_KEY, digest));
+            let submit = |token: &mut PspCoin| {
+                token.transfer_with_authorization(
+                    from,
+                    accounts.bob,
+                    U256::from(100),
+                    1_000,
+                    2_000,
+                    nonce,
+                    v,
+                    r,
+                    s,
+                )
+            };
+
+            // The window is in seconds, block timestamps in milliseconds
+            assert_eq!(submit(&mut token), Err(PSP22Error::AuthorizationNotYetValid));
+            set_block_timestamp(1_000_999);
+            assert_eq!(submit(&mut token), Err(PSP22Error::AuthorizationNotYetValid));
+            set_block_timestamp(2_000_000);
+            assert_eq!(submit(&mut token), Err(PSP22Error::AuthorizationExpired));
+            set_block_timestamp(1_999_999);
+            assert!(submit(&mut token).is_ok());
+        }
+
+        #[ink::test]
+        fn receive_with_authorization_requires_payee() {
+            let accounts = default_accounts();
+            let (mut token, from) = new_authorization_token();
+            let nonce = H256::repeat_byte(3);
+
+            let digest = token.authorization_digest(
+                RECEIVE_WITH_AUTHORIZATION_TYPE,
+                from,
+                accounts.bob,
+                U256::from(100),
+                0,
+                2_000,
+                nonce,
+            );
+            let (v, r, s) = split_signature(sign(AUTHORIZATION_KEY, digest));
+
+            set_caller(accounts.charlie);
+            assert_eq!(
+                token.receive_with_authorization(
+                    from,
+                    accounts.bob,
+                    U256::from(100),
+                    0,
+                    2_000,
+                    nonce,
+                    v,
+                    r,
+                    s
+                ),
+                Err(PSP22Error::Unauthorized)
+            );
+
+            // A receive authorization can't be used as a transfer authorization
+            assert_eq!(
+                token.transfer_with_authorization(
+                    from,
+                    accounts.bob,
+                    U256::from(100),
+                    0,
+                    2_000,
+                    nonce,
+                    v,
+                    r,
+                    s
+                ),
+                Err(PSP22Error::InvalidSignature)
+            );
+
+            set_caller(accounts.bob);
+            assert!(
+                token
+                    .receive_with_authorization(
+                        from,
+                        accounts.bob,
+                        U256::from(100),
+                        0,
+                        2_000,
+                        nonce,
+                        v,
+                        r,
+                        s
+                    )
+                    .is_ok()
+            );
+            assert_eq!(token.balance_of(accounts.bob), U256::from(100));
         }
 
         #[ink::test]
-        fn new_with_supply_works() {
+        fn cancel_authorization_burns_nonce() {
             let accounts = default_accounts();
-            set_caller(accounts.alice);
+            let (mut token, from) = new_authorization_token();
+            let nonce = H256::repeat_byte(4);
 
-            let initial_supply = U256::from(1000000);
-            let token = PspCoin::new_with_supply(initial_supply);
+            let digest = token.eip712_digest(
+                CANCEL_AUTHORIZATION_TYPE,
+                &[PspCoin::address_word(from), nonce.0],
+            );
+            let (v, r, s) = split_signature(sign(AUTHORIZATION_KEY, digest));
 
-            assert_eq!(token.total_supply(), initial_supply);
-            assert_eq!(token.balance_of(accounts.alice), initial_supply);
-            assert_eq!(token.balance_of(accounts.bob), U256::from(0));
+            set_caller(accounts.bob);
+            assert_eq!(
+                token.cancel_authorization(accounts.bob, nonce, v, r, s),
+                Err(PSP22Error::InvalidSignature)
+            );
+            assert!(token.cancel_authorization(from, nonce, v, r, s).is_ok());
+            assert!(token.authorization_state(from, nonce));
+            assert_eq!(
+                token.cancel_authorization(from, nonce, v, r, s),
+                Err(PSP22Error::AuthorizationAlreadyUsed)
+            );
+
+            let digest = token.authorization_digest(
+                TRANSFER_WITH_AUTHORIZATION_TYPE,
+                from,
+                accounts.bob,
+                U256::from(100),
+                0,
+                2_000,
+                nonce,
+            );
+            let (v, r, s) = split_signature(sign(AUTHORIZATION_KEY, digest));
+            assert_eq!(
+                token.transfer_with_authorization(
+                    from,
+                    accounts.bob,
+                    U256::from(100),
+                    0,
+                    2_000,
+                    nonce,
+                    v,
+                    r,
+                    s
+                ),
+                Err(PSP22Error::AuthorizationAlreadyUsed)
+            );
         }
 
         #[ink::test]
-        fn transfer_works() {
+        fn operator_send_requires_authorization() {
             let accounts = default_accounts();
             set_caller(accounts.alice);
 
-            let initial_supply = U256::from(1000);
-            let mut token = PspCoin::new_with_supply(initial_supply);
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            assert!(token.is_operator_for(accounts.alice, accounts.alice));
+            assert!(!token.is_operator_for(accounts.bob, accounts.alice));
+            assert_eq!(
+                token.authorize_operator(accounts.alice),
+                Err(PSP22Error::InvalidOperator)
+            );
 
-            let transfer_amount = U256::from(100);
+            set_caller(accounts.bob);
+            assert_eq!(
+                token.operator_send(accounts.alice, accounts.bob, U256::from(100), Vec::new()),
+                Err(PSP22Error::Unauthorized)
+            );
+
+            set_caller(accounts.alice);
+            assert!(token.authorize_operator(accounts.bob).is_ok());
+            assert!(token.is_operator_for(accounts.bob, accounts.alice));
+
+            // Operators aren't bound by an amount
+            set_caller(accounts.bob);
             assert!(
                 token
-                    .transfer(accounts.bob, transfer_amount, Vec::new())
+                    .operator_send(accounts.alice, accounts.charlie, U256::from(700), Vec::new())
                     .is_ok()
             );
+            assert_eq!(token.balance_of(accounts.charlie), U256::from(700));
+            assert_eq!(token.allowance(accounts.alice, accounts.bob), U256::from(0));
 
-            assert_eq!(token.balance_of(accounts.alice), U256::from(900));
-            assert_eq!(token.balance_of(accounts.bob), U256::from(100));
-        }
-
-        #[ink::test]
-        fn transfer_insufficient_balance_fails() {
-            let accounts = default_accounts();
             set_caller(accounts.alice);
-
-            let initial_supply = U256::from(100);
-            let mut token = PspCoin::new_with_supply(initial_supply);
-
-            let transfer_amount = U256::from(200);
+            assert!(token.revoke_operator(accounts.bob).is_ok());
+            set_caller(accounts.bob);
             assert_eq!(
-                token.transfer(accounts.bob, transfer_amount, Vec::new()),
-                Err(PSP22Error::InsufficientBalance)
+                token.operator_send(accounts.alice, accounts.bob, U256::from(100), Vec::new()),
+                Err(PSP22Error::Unauthorized)
             );
         }
 
         #[ink::test]
-        fn transfer_to_self_works() {
+        fn default_operators_can_be_revoked_per_holder() {
             let accounts = default_accounts();
             set_caller(accounts.alice);
 
-            let initial_supply = U256::from(1000);
-            let mut token = PspCoin::new_with_supply(initial_supply);
+            let mut token =
+                PspCoin::new_with_operators(U256::from(1000), ink::prelude::vec![accounts.django]);
+            assert!(token.transfer(accounts.bob, U256::from(500), Vec::new()).is_ok());
+            assert_eq!(token.default_operators(), ink::prelude::vec![accounts.django]);
+            assert!(token.is_operator_for(accounts.django, accounts.alice));
+            assert!(token.is_operator_for(accounts.django, accounts.bob));
+            assert!(!token.is_operator_for(accounts.django, ink::env::address()));
 
+            set_caller(accounts.bob);
+            assert!(token.revoke_operator(accounts.django).is_ok());
+            assert!(!token.is_operator_for(accounts.django, accounts.bob));
+            assert!(token.is_operator_for(accounts.django, accounts.alice));
+
+            set_caller(accounts.django);
+            assert_eq!(
+                token.operator_send(accounts.bob, accounts.django, U256::from(100), Vec::new()),
+                Err(PSP22Error::Unauthorized)
+            );
             assert!(
                 token
-                    .transfer(accounts.alice, U256::from(100), Vec::new())
+                    .operator_send(accounts.alice, accounts.django, U256::from(100), Vec::new())
                     .is_ok()
             );
-            assert_eq!(token.balance_of(accounts.alice), initial_supply);
+
+            set_caller(accounts.bob);
+            assert!(token.authorize_operator(accounts.django).is_ok());
+            assert!(token.is_operator_for(accounts.django, accounts.bob));
         }
 
         #[ink::test]
-        fn approve_works() {
+        fn vault_deposit_and_redeem_round_trip() {
             let accounts = default_accounts();
             set_caller(accounts.alice);
 
-            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            let mut token = PspCoin::new_with_supply(U256::from(10_000));
 
-            let allowance_amount = U256::from(200);
-            assert!(token.approve(accounts.bob, allowance_amount).is_ok());
+            assert_eq!(
+                token.vault_deposit(U256::from(1_000), accounts.alice),
+                Ok(U256::from(1_000_000))
+            );
+            assert_eq!(token.total_assets(), U256::from(1_000));
+            assert_eq!(token.share_balance_of(accounts.alice), U256::from(1_000_000));
+            assert_eq!(token.balance_of(accounts.alice), U256::from(9_000));
+            assert_eq!(token.custody_balance(), U256::from(1_000));
 
             assert_eq!(
-                token.allowance(accounts.alice, accounts.bob),
-                allowance_amount
+                token.vault_redeem(U256::from(1_000_000), accounts.bob, accounts.alice),
+                Ok(U256::from(1_000))
             );
+            assert_eq!(token.balance_of(accounts.bob), U256::from(1_000));
+            assert_eq!(token.share_total_supply(), U256::from(0));
+            assert_eq!(token.total_assets(), U256::from(0));
         }
 
         #[ink::test]
-        fn approve_self_works() {
+        fn vault_rounds_in_favor_of_the_vault() {
             let accounts = default_accounts();
             set_caller(accounts.alice);
 
-            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            let mut token = PspCoin::new_with_supply(U256::from(10_000));
+            assert!(token.vault_deposit(U256::from(1_000), accounts.alice).is_ok());
+            assert!(token.deposit_vault_yield(U256::from(500)).is_ok());
+
+            // 1 asset is worth 666.9 shares
+            assert_eq!(token.preview_deposit(U256::from(1)), Ok(U256::from(666)));
+            assert_eq!(token.preview_withdraw(U256::from(1)), Ok(U256::from(667)));
+            assert_eq!(token.preview_mint(U256::from(666)), Ok(U256::from(1)));
+            assert_eq!(token.preview_redeem(U256::from(666)), Ok(U256::from(0)));
 
-            assert!(token.approve(accounts.alice, U256::from(100)).is_ok());
             assert_eq!(
-                token.allowance(accounts.alice, accounts.alice),
-                U256::from(0)
+                token.vault_redeem(U256::from(666), accounts.alice, accounts.alice),
+                Err(PSP22Error::ZeroAmount)
             );
+            assert_eq!(token.vault_mint(U256::from(666), accounts.bob), Ok(U256::from(1)));
+            assert_eq!(token.max_redeem(accounts.bob), U256::from(666));
         }
 
         #[ink::test]
-        fn transfer_from_works() {
+        fn vault_resists_first_depositor_inflation() {
             let accounts = default_accounts();
             set_caller(accounts.alice);
 
-            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            let mut token = PspCoin::new_with_supply(U256::from(100_000));
+            assert!(token.transfer(accounts.bob, U256::from(10_000), Vec::new()).is_ok());
 
-            // Alice approves Bob to spend 200 tokens
-            assert!(token.approve(accounts.bob, U256::from(200)).is_ok());
+            // The attacker deposits 1 asset and donates 10_000 to inflate the share price
+            set_caller(accounts.alice);
+            assert!(token.vault_deposit(U256::from(1), accounts.alice).is_ok());
+            assert!(token.deposit_vault_yield(U256::from(10_000)).is_ok());
 
-            // Bob transfers 100 tokens from Alice to Charlie
+            // The victim still gets shares worth nearly the full deposit
             set_caller(accounts.bob);
-            assert!(
-                token
-                    .transfer_from(
-                        accounts.alice,
-                        accounts.charlie,
-                        U256::from(100),
-                        Vec::new()
-                    )
-                    .is_ok()
-            );
-
-            assert_eq!(token.balance_of(accounts.alice), U256::from(900));
-            assert_eq!(token.balance_of(accounts.charlie), U256::from(100));
+            let shares = token.vault_deposit(U256::from(10_000), accounts.bob).unwrap();
+            assert_eq!(shares, U256::from(1_999));
             assert_eq!(
-                token.allowance(accounts.alice, accounts.bob),
-                U256::from(100)
+                token.vault_redeem(shares, accounts.bob, accounts.bob),
+                Ok(U256::from(9_998))
             );
+
+            // while the attacker gets back about half of what they put in
+            set_caller(accounts.alice);
+            let attacker_shares = token.share_balance_of(accounts.alice);
+            assert!(token.preview_redeem(attacker_shares).unwrap() < U256::from(5_100));
         }
 
         #[ink::test]
-        fn transfer_from_insufficient_allowance_fails() {
+        fn vault_withdraw_on_behalf_needs_share_allowance() {
             let accounts = default_accounts();
             set_caller(accounts.alice);
 
-            let mut token = PspCoin::new_with_supply(U256::from(1000));
-
-            // Alice approves Bob to spend 50 tokens
-            assert!(token.approve(accounts.bob, U256::from(50)).is_ok());
+            let mut token = PspCoin::new_with_supply(U256::from(10_000));
+            assert!(token.vault_deposit(U256::from(1_000), accounts.alice).is_ok());
 
-            // Bob tries to transfer 100 tokens from Alice
             set_caller(accounts.bob);
             assert_eq!(
-                token.transfer_from(
-                    accounts.alice,
-                    accounts.charlie,
-                    U256::from(100),
-                    Vec::new()
-                ),
+                token.vault_withdraw(U256::from(100), accounts.bob, accounts.alice),
                 Err(PSP22Error::InsufficientAllowance)
             );
+
+            set_caller(accounts.alice);
+            assert!(token.share_approve(accounts.bob, U256::from(100_000)).is_ok());
+
+            set_caller(accounts.bob);
+            assert_eq!(
+                token.vault_withdraw(U256::from(100), accounts.bob, accounts.alice),
+                Ok(U256::from(100_000))
+            );
+            assert_eq!(token.balance_of(accounts.bob), U256::from(100));
+            assert_eq!(token.share_allowance(accounts.alice, accounts.bob), U256::from(0));
+            assert_eq!(token.max_withdraw(accounts.alice), Ok(U256::from(900)));
+
+            // Shares are transferable like any PSP22 token
+            set_caller(accounts.alice);
+            assert!(token.share_transfer(accounts.charlie, U256::from(1_000)).is_ok());
+            assert_eq!(token.share_balance_of(accounts.charlie), U256::from(1_000));
         }
 
-        #[ink::test]
-        fn transfer_from_by_owner_works() {
+        const RELAYER_KEYS: [[u8; 32]; 3] = [[21u8; 32], [22u8; 32], [23u8; 32]];
+        const REMOTE_CHAIN: u64 = 2;
+
+        /// Alice deploys with 1000 tokens, 2-of-3 relayers and `REMOTE_CHAIN` capped at 1000/s
+        fn new_bridge_token() -> PspCoin {
             let accounts = default_accounts();
             set_caller(accounts.alice);
+            set_block_timestamp(1_000);
 
-            let mut token = PspCoin::new_with_supply(U256::from(1000));
-
-            // Alice transfers her own tokens without approval
-            assert!(
-                token
-                    .transfer_from(accounts.alice, accounts.bob, U256::from(100), Vec::new())
-                    .is_ok()
-            );
+            let mut token = PspCoin::new_with_chain_id(U256::from(1_000), CHAIN_ID);
+            for key in RELAYER_KEYS {
+                assert!(token.add_relayer(address_of(key)).is_ok());
+            }
+            assert!(token.set_relayer_threshold(2).is_ok());
+            assert!(token.set_bridge_rate_limit(REMOTE_CHAIN, U256::from(1_000), 1_000).is_ok());
+            token
+        }
 
-            assert_eq!(token.balance_of(accounts.alice), U256::from(900));
-            assert_eq!(token.balance_of(accounts.bob), U256::from(100));
+        /// Relayer signatures over an inbound transfer, one per key
+        fn attest(
+            token: &PspCoin,
+            keys: &[[u8; 32]],
+            nonce: u64,
+            recipient: H160,
+            amount: U256,
+        ) -> Vec<[u8; 65]> {
+            let message_hash = token.bridge_message_hash(REMOTE_CHAIN, nonce, recipient, amount);
+            keys.iter().map(|key| sign(*key, message_hash)).collect()
         }
 
         #[ink::test]
-        fn increase_allowance_works() {
+        fn bridge_in_requires_relayer_threshold() {
             let accounts = default_accounts();
-            set_caller(accounts.alice);
+            let mut token = new_bridge_token();
+            let amount = U256::from(100);
 
-            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            set_caller(accounts.charlie);
+            let one = attest(&token, &RELAYER_KEYS[..1], 0, accounts.bob, amount);
+            assert_eq!(
+                token.bridge_in(REMOTE_CHAIN, 0, accounts.bob, amount, one.clone()),
+                Err(PSP22Error::NotEnoughAttestations)
+            );
+
+            // The same relayer twice and a stranger don't make a quorum
+            let mut padded = one.clone();
+            padded.extend(one);
+            padded.extend(attest(&token, &[[99u8; 32]], 0, accounts.bob, amount));
+            assert_eq!(
+                token.bridge_in(REMOTE_CHAIN, 0, accounts.bob, amount, padded),
+                Err(PSP22Error::NotEnoughAttestations)
+            );
+
+            // Signatures for another amount don't count either
+            let signatures = attest(&token, &RELAYER_KEYS[..2], 0, accounts.bob, amount);
+            assert_eq!(
+                token.bridge_in(REMOTE_CHAIN, 0, accounts.bob, U256::from(200), signatures.clone()),
+                Err(PSP22Error::NotEnoughAttestations)
+            );
+            assert_eq!(
+                token.bridge_in(REMOTE_CHAIN + 1, 0, accounts.bob, amount, signatures.clone()),
+                Err(PSP22Error::UnsupportedChain)
+            );
 
-            assert!(token.approve(accounts.bob, U256::from(100)).is_ok());
             assert!(
                 token
-                    .increase_allowance(accounts.bob, U256::from(50))
+                    .bridge_in(REMOTE_CHAIN, 0, accounts.bob, amount, signatures.clone())
                     .is_ok()
             );
+            assert_eq!(token.balance_of(accounts.bob), amount);
+            assert_eq!(token.total_supply(), U256::from(1_100));
+            assert!(token.is_bridge_message_processed(REMOTE_CHAIN, 0));
 
             assert_eq!(
-                token.allowance(accounts.alice, accounts.bob),
-                U256::from(150)
+                token.bridge_in(REMOTE_CHAIN, 0, accounts.bob, amount, signatures),
+                Err(PSP22Error::BridgeMessageProcessed)
             );
         }
 
         #[ink::test]
-        fn decrease_allowance_works() {
+        fn bridge_in_is_rate_limited_per_chain() {
             let accounts = default_accounts();
-            set_caller(accounts.alice);
+            let mut token = new_bridge_token();
 
-            let mut token = PspCoin::new_with_supply(U256::from(1000));
-
-            assert!(token.approve(accounts.bob, U256::from(100)).is_ok());
+            let signatures = attest(&token, &RELAYER_KEYS[1..], 0, accounts.bob, U256::from(800));
             assert!(
                 token
-                    .decrease_allowance(accounts.bob, U256::from(50))
+                    .bridge_in(REMOTE_CHAIN, 0, accounts.bob, U256::from(800), signatures)
                     .is_ok()
             );
 
+            let signatures = attest(&token, &RELAYER_KEYS[1..], 1, accounts.bob, U256::from(300));
             assert_eq!(
-                token.allowance(accounts.alice, accounts.bob),
-                U256::from(50)
+                token.bridge_in(REMOTE_CHAIN, 1, accounts.bob, U256::from(300), signatures.clone()),
+                Err(PSP22Error::RateLimitExceeded)
+            );
+
+            // A new window starts once the period has passed
+            set_block_timestamp(2_000);
+            assert!(
+                token
+                    .bridge_in(REMOTE_CHAIN, 1, accounts.bob, U256::from(300), signatures)
+                    .is_ok()
             );
+            assert_eq!(token.balance_of(accounts.bob), U256::from(1_100));
         }
 
         #[ink::test]
-        fn decrease_allowance_below_zero_fails() {
+        fn bridge_rate_limit_needs_a_period() {
             let accounts = default_accounts();
-            set_caller(accounts.alice);
+            let mut token = new_bridge_token();
 
-            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            assert_eq!(
+                token.set_bridge_rate_limit(REMOTE_CHAIN, U256::from(1_000), 0),
+                Err(PSP22Error::InvalidParameter)
+            );
+            let rate_limit = token.bridge_rate_limit(REMOTE_CHAIN);
+            assert_eq!(rate_limit.map(|limit| limit.period), Some(1_000));
 
-            assert!(token.approve(accounts.bob, U256::from(50)).is_ok());
+            // Splitting a transfer doesn't get around the limit within a window
+            let signatures = attest(&token, &RELAYER_KEYS[1..], 0, accounts.bob, U256::from(600));
+            assert!(
+                token
+                    .bridge_in(REMOTE_CHAIN, 0, accounts.bob, U256::from(600), signatures)
+                    .is_ok()
+            );
+            let signatures = attest(&token, &RELAYER_KEYS[1..], 1, accounts.bob, U256::from(600));
             assert_eq!(
-                token.decrease_allowance(accounts.bob, U256::from(100)),
-                Err(PSP22Error::InsufficientAllowance)
+                token.bridge_in(REMOTE_CHAIN, 1, accounts.bob, U256::from(600), signatures),
+                Err(PSP22Error::RateLimitExceeded)
+            );
+
+            // Removing a chain doesn't need a period
+            assert!(
+                token
+                    .set_bridge_rate_limit(REMOTE_CHAIN, U256::from(0), 0)
+                    .is_ok()
             );
+            assert_eq!(token.bridge_rate_limit(REMOTE_CHAIN), None);
         }
 
         #[ink::test]
-        fn mint_works() {
+        fn bridge_out_burns_with_nonce() {
             let accounts = default_accounts();
-            set_caller(accounts.alice);
-
-            let mut token = PspCoin::new();
+            let mut token = new_bridge_token();
+            let recipient = ink::prelude::vec![0xab; 20];
 
-            assert!(token.mint(U256::from(1000)).is_ok());
-            assert_eq!(token.total_supply(), U256::from(1000));
-            assert_eq!(token.balance_of(accounts.alice), U256::from(1000));
+            assert_eq!(
+                token.bridge_out(REMOTE_CHAIN + 1, recipient.clone(), U256::from(100)),
+                Err(PSP22Error::UnsupportedChain)
+            );
+            assert_eq!(
+                token.bridge_out(REMOTE_CHAIN, Vec::new(), U256::from(100)),
+                Err(PSP22Error::InvalidRecipient)
+            );
 
-            assert!(token.mint(U256::from(500)).is_ok());
-            assert_eq!(token.total_supply(), U256::from(1500));
-            assert_eq!(token.balance_of(accounts.alice), U256::from(1500));
+            assert_eq!(token.bridge_out(REMOTE_CHAIN, recipient.clone(), U256::from(100)), Ok(0));
+            assert_eq!(token.bridge_out(REMOTE_CHAIN, recipient, U256::from(100)), Ok(1));
+            assert_eq!(token.outbound_nonce(REMOTE_CHAIN), 2);
+            assert_eq!(token.balance_of(accounts.alice), U256::from(800));
+            assert_eq!(token.total_supply(), U256::from(800));
         }
 
         #[ink::test]
-        fn mint_zero_is_noop() {
+        fn relayer_set_is_owner_managed() {
             let accounts = default_accounts();
-            set_caller(accounts.alice);
+            let mut token = new_bridge_token();
+            assert_eq!(token.relayer_threshold(), (2, 3));
 
-            let mut token = PspCoin::new();
+            set_caller(accounts.bob);
+            assert_eq!(token.add_relayer(accounts.bob), Err(PSP22Error::Unauthorized));
 
-            assert!(token.mint(U256::from(0)).is_ok());
-            assert_eq!(token.total_supply(), U256::from(0));
-            assert_eq!(token.balance_of(accounts.alice), U256::from(0));
+            set_caller(accounts.alice);
+            assert_eq!(
+                token.add_relayer(address_of(RELAYER_KEYS[0])),
+                Err(PSP22Error::AlreadyRelayer)
+            );
+            assert_eq!(token.set_relayer_threshold(4), Err(PSP22Error::InvalidThreshold));
+
+            assert!(token.set_relayer_threshold(3).is_ok());
+            assert_eq!(
+                token.remove_relayer(address_of(RELAYER_KEYS[0])),
+                Err(PSP22Error::InvalidThreshold)
+            );
+            assert!(token.set_relayer_threshold(2).is_ok());
+            assert!(token.remove_relayer(address_of(RELAYER_KEYS[0])).is_ok());
+            assert!(!token.is_relayer(address_of(RELAYER_KEYS[0])));
         }
 
         #[ink::test]
-        fn burn_works() {
+        fn bridge_in_is_bound_to_chain_id() {
             let accounts = default_accounts();
-            set_caller(accounts.alice);
-
-            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            let mut token = new_bridge_token();
+            let amount = U256::from(100);
+            let signatures = attest(&token, &RELAYER_KEYS, 0, accounts.bob, amount);
 
-            assert!(token.burn(U256::from(300)).is_ok());
-            assert_eq!(token.total_supply(), U256::from(700));
-            assert_eq!(token.balance_of(accounts.alice), U256::from(700));
+            // The same contract address deployed on another chain
+            token.chain_id.set(&(CHAIN_ID + 1));
+            assert_eq!(
+                token.bridge_in(REMOTE_CHAIN, 0, accounts.bob, amount, signatures),
+                Err(PSP22Error::NotEnoughAttestations)
+            );
+            assert_eq!(token.balance_of(accounts.bob), U256::from(0));
         }
 
         #[ink::test]
-        fn burn_insufficient_balance_fails() {
+        fn signed_messages_need_chain_id() {
             let accounts = default_accounts();
             set_caller(accounts.alice);
 
-            let mut token = PspCoin::new_with_supply(U256::from(100));
+            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            assert_eq!(token.chain_id(), 0);
 
+            let nonce = H256::repeat_byte(4);
+            let (v, r, s) = (27, H256::zero(), H256::zero());
             assert_eq!(
-                token.burn(U256::from(200)),
-                Err(PSP22Error::InsufficientBalance)
+                token.transfer_with_authorization(
+                    accounts.alice,
+                    accounts.bob,
+                    U256::from(100),
+                    0,
+                    2_000,
+                    nonce,
+                    v,
+                    r,
+                    s,
+                ),
+                Err(PSP22Error::ChainIdNotSet)
+            );
+            assert_eq!(
+                token.cancel_authorization(accounts.alice, nonce, v, r, s),
+                Err(PSP22Error::ChainIdNotSet)
+            );
+
+            assert!(
+                token
+                    .set_bridge_rate_limit(2, U256::from(1000), 1_000)
+                    .is_ok()
+            );
+            assert_eq!(
+                token.bridge_in(2, 0, accounts.bob, U256::from(100), Vec::new()),
+                Err(PSP22Error::ChainIdNotSet)
             );
         }
 
         #[ink::test]
-        fn burn_zero_is_noop() {
+        fn chain_id_constructors_set_chain_id() {
             let accounts = default_accounts();
             set_caller(accounts.alice);
 
-            let mut token = PspCoin::new_with_supply(U256::from(1000));
+            let token = PspCoin::new_with_chain_id(U256::from(1000), CHAIN_ID);
+            assert_eq!(token.chain_id(), CHAIN_ID);
+            assert_eq!(token.balance_of(accounts.alice), U256::from(1000));
 
-            assert!(token.burn(U256::from(0)).is_ok());
-            assert_eq!(token.total_supply(), U256::from(1000));
+            let token = PspCoin::new_wrapped_with_chain_id(
+                String::from("Wrapped Native"),
+                String::from("WNAT"),
+                18,
+                CHAIN_ID,
+            );
+            assert_eq!(token.chain_id(), CHAIN_ID);
+            assert_eq!(token.symbol(), Some(String::from("WNAT")));
+
+            let token = PspCoin::new_with_operators_and_chain_id(
+                U256::from(1000),
+                ink::prelude::vec![accounts.django],
+                CHAIN_ID,
+            );
+            assert_eq!(token.chain_id(), CHAIN_ID);
+            assert!(token.is_operator_for(accounts.django, accounts.alice));
         }
 
         #[ink::test]
-        fn metadata_works() {
-            let token = PspCoin::new();
-
-            assert_eq!(token.name(), Some(String::from("MemeCoin")));
-            assert_eq!(token.symbol(), Some(String::from("MEME")));
-            assert_eq!(token.decimals(), 18);
+        #[should_panic(expected = "ChainIdNotSet")]
+        fn constructor_rejects_zero_chain_id() {
+            set_caller(default_accounts().alice);
+            PspCoin::new_with_chain_id(U256::from(1000), 0);
         }
     }
 }
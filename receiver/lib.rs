@@ -0,0 +1,238 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+//! `PSP22Receiver` used by the end-to-end tests of `transfer_and_call` and
+//! `approve_and_call`, which live in this crate so it can depend on the token.
+
+pub use self::mock_receiver::{MockReceiverRef, Mode};
+
+#[ink::contract]
+mod mock_receiver {
+    use ink::env::call::{ExecutionInput, Selector, build_call};
+    use ink::prelude::vec::Vec;
+    use ink::{H160, U256};
+    use psp_coin::PSP22Error;
+
+    /// How the receiver answers the token's hooks
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub enum Mode {
+        /// Accepts by returning the hook's selector.
+        Accept,
+        /// Rejects by returning anything else.
+        Reject,
+        /// Pulls approved tokens with `transfer_from` before accepting. A pull
+        /// the token refuses is kept in `last_error` and still accepted, since
+        /// rejecting would revert the record.
+        Pull,
+    }
+
+    #[ink(storage)]
+    pub struct MockReceiver {
+        mode: Mode,
+        last_error: Option<PSP22Error>,
+    }
+
+    impl MockReceiver {
+        /// Constructor that answers every hook according to `mode`
+        #[ink(constructor)]
+        pub fn new(mode: Mode) -> Self {
+            Self {
+                mode,
+                last_error: None,
+            }
+        }
+
+        /// Returns the error the token returned for the last pull, if it failed
+        #[ink(message)]
+        pub fn last_error(&self) -> Option<PSP22Error> {
+            self.last_error.clone()
+        }
+
+        /// Hook invoked by `transfer_and_call`
+        #[ink(message)]
+        pub fn on_transfer_received(
+            &mut self,
+            _operator: H160,
+            _from: H160,
+            _value: U256,
+            _data: Vec<u8>,
+        ) -> [u8; 4] {
+            self.answer(ink::selector_bytes!("on_transfer_received"))
+        }
+
+        /// Hook invoked by `approve_and_call`
+        #[ink(message)]
+        pub fn on_approval_received(
+            &mut self,
+            owner: H160,
+            value: U256,
+            _data: Vec<u8>,
+        ) -> [u8; 4] {
+            if self.mode == Mode::Pull {
+                // The token is the caller and has to allow re-entry for this to work
+                let token = self.env().caller();
+                let pulled = build_call::<Environment>()
+                    .call(token)
+                    .exec_input(
+                        ExecutionInput::new(Selector::new(ink::selector_bytes!("transfer_from")))
+                            .push_arg(owner)
+                            .push_arg(self.env().address())
+                            .push_arg(value)
+                            .push_arg(Vec::<u8>::new()),
+                    )
+                    .returns::<Result<(), PSP22Error>>()
+                    .try_invoke();
+
+                match pulled {
+                    Ok(Ok(result)) => self.last_error = result.err(),
+                    // The call itself failed, so there is no token error to keep
+                    _ => return [0u8; 4],
+                }
+            }
+
+            self.answer(ink::selector_bytes!("on_approval_received"))
+        }
+
+        /// Returns `selector` to accept, unless the receiver rejects everything
+        fn answer(&self, selector: [u8; 4]) -> [u8; 4] {
+            match self.mode {
+                Mode::Reject => [0u8; 4],
+                Mode::Accept | Mode::Pull => selector,
+            }
+        }
+    }
+
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use ink_e2e::ContractsBackend;
+        use psp_coin::{PspCoin, PspCoinRef};
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        #[ink_e2e::test]
+        async fn transfer_and_call_needs_receiver_to_accept<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            let mut constructor = PspCoinRef::new_with_supply(U256::from(1_000));
+            let token = client
+                .instantiate("psp_coin", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("token instantiate failed");
+            let mut call_builder = token.call_builder::<PspCoin>();
+
+            let mut constructor = MockReceiverRef::new(Mode::Accept);
+            let accepting = client
+                .instantiate("mock_receiver", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("receiver instantiate failed");
+            let mut constructor = MockReceiverRef::new(Mode::Reject);
+            let rejecting = client
+                .instantiate("mock_receiver", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("receiver instantiate failed");
+
+            let value = U256::from(100);
+            let transfer = call_builder.transfer_and_call(accepting.addr, value, Vec::new());
+            let result = client.call(&ink_e2e::alice(), &transfer).submit().await?;
+            assert_eq!(result.return_value(), Ok(()));
+
+            let transfer = call_builder.transfer_and_call(rejecting.addr, value, Vec::new());
+            let result = client.call(&ink_e2e::alice(), &transfer).dry_run().await?;
+            assert_eq!(result.return_value(), Err(PSP22Error::ReceiverRejected));
+
+            let balance = call_builder.balance_of(accepting.addr);
+            let result = client.call(&ink_e2e::alice(), &balance).dry_run().await?;
+            assert_eq!(result.return_value(), value);
+            let balance = call_builder.balance_of(rejecting.addr);
+            let result = client.call(&ink_e2e::alice(), &balance).dry_run().await?;
+            assert_eq!(result.return_value(), U256::from(0));
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn approve_and_call_lets_spender_pull_tokens<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            let mut constructor = PspCoinRef::new_with_supply(U256::from(1_000));
+            let token = client
+                .instantiate("psp_coin", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("token instantiate failed");
+            let mut call_builder = token.call_builder::<PspCoin>();
+
+            let mut constructor = MockReceiverRef::new(Mode::Pull);
+            let spender = client
+                .instantiate("mock_receiver", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("receiver instantiate failed");
+
+            // The spender re-enters the token with `transfer_from` from inside its hook
+            let approve = call_builder.approve_and_call(spender.addr, U256::from(300), Vec::new());
+            let result = client.call(&ink_e2e::alice(), &approve).submit().await?;
+            assert_eq!(result.return_value(), Ok(()));
+
+            let balance = call_builder.balance_of(spender.addr);
+            let result = client.call(&ink_e2e::alice(), &balance).dry_run().await?;
+            assert_eq!(result.return_value(), U256::from(300));
+
+            let error = spender.call_builder::<MockReceiver>().last_error();
+            let result = client.call(&ink_e2e::alice(), &error).dry_run().await?;
+            assert_eq!(result.return_value(), None);
+
+            // The spender became a holder inside the hook; the outer call must not undo that
+            let holder_count = call_builder.holder_count();
+            let result = client
+                .call(&ink_e2e::alice(), &holder_count)
+                .dry_run()
+                .await?;
+            assert_eq!(result.return_value(), 2);
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn approve_and_call_reports_failed_pull<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            let mut constructor = PspCoinRef::new_with_supply(U256::from(1_000));
+            let token = client
+                .instantiate("psp_coin", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("token instantiate failed");
+            let mut call_builder = token.call_builder::<PspCoin>();
+
+            let mut constructor = MockReceiverRef::new(Mode::Pull);
+            let spender = client
+                .instantiate("mock_receiver", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("receiver instantiate failed");
+            let spender_builder = spender.call_builder::<MockReceiver>();
+
+            // The allowance exceeds Alice's balance, so the token refuses the pull
+            let approve =
+                call_builder.approve_and_call(spender.addr, U256::from(2_000), Vec::new());
+            let result = client.call(&ink_e2e::alice(), &approve).submit().await?;
+            assert_eq!(result.return_value(), Ok(()));
+
+            let error = spender_builder.last_error();
+            let result = client.call(&ink_e2e::alice(), &error).dry_run().await?;
+            assert_eq!(result.return_value(), Some(PSP22Error::InsufficientBalance));
+
+            let balance = call_builder.balance_of(spender.addr);
+            let result = client.call(&ink_e2e::alice(), &balance).dry_run().await?;
+            assert_eq!(result.return_value(), U256::from(0));
+
+            Ok(())
+        }
+    }
+}
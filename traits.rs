@@ -51,7 +51,8 @@ pub trait PSP22 {
     ///
     /// If `from` and the caller are different addresses, a successful transfer results
     /// in decreased allowance by `from` to the caller and an `Approval` event with
-    /// the new allowance amount is emitted.
+    /// the new allowance amount is emitted. An allowance of `U256::MAX` is treated as
+    /// unlimited: it is not decreased and no `Approval` event is emitted.
     ///
     /// # Errors
     ///
@@ -148,6 +149,8 @@ pub trait PSP22Burnable {
 pub trait PSP22Mintable {
     /// Mints `value` tokens to the senders account.
     ///
+    /// Restricted to the owner of the token.
+    ///
     /// The selector for this message is `0xfc3c75d4` (first 4 bytes of `blake2b_256("PSP22Mintable::mint")`).
     ///
     /// # Events
@@ -158,8 +161,11 @@ pub trait PSP22Mintable {
     ///
     /// # Errors
     ///
-    /// Reverts with `Custom (max supply exceeded)` if the total supply increased by
-    /// `value` exceeds maximal value of `u128` type.
+    /// Reverts with `Unauthorized` if the caller isn't the owner, and with `Timelocked`
+    /// if the timelock is enabled, in which case minting has to be scheduled.
+    ///
+    /// Reverts with `Overflow` if the total supply increased by `value` exceeds
+    /// maximal value of `U256` type.
     fn mint(&mut self, value: U256) -> Result<(), PSP22Error>;
 }
 
@@ -171,4 +177,54 @@ pub trait PSP22Permit {
     fn permit(&mut self, owner: H160, spender: H160, value: U256, deadline: u64, v: u8, r: [u8; 32], s: [u8; 32]) -> Result<(), PSP22Error>;
 
     fn nonces(&self, owner: H160) -> u128;
-}
\ No newline at end of file
+}
+
+#[allow(dead_code)]
+pub trait PSP22Callable {
+    /// Transfers `value` tokens from the caller to `to` and then calls
+    /// `on_transfer_received` on `to` (ERC-1363 `transferAndCall`).
+    ///
+    /// # Events
+    ///
+    /// On success a `Transfer` event is emitted.
+    ///
+    /// # Errors
+    ///
+    /// Reverts with `InsufficientBalance` if the `value` exceeds the caller's balance.
+    ///
+    /// Reverts with `ReceiverRejected` if `to` can't be called or doesn't
+    /// return the `on_transfer_received` selector.
+    fn transfer_and_call(&mut self, to: H160, value: U256, data: Vec<u8>) -> Result<(), PSP22Error>;
+
+    /// Approves `spender` to spend `value` tokens and then calls
+    /// `on_approval_received` on `spender` (ERC-1363 `approveAndCall`).
+    ///
+    /// # Events
+    ///
+    /// An `Approval` event is emitted.
+    ///
+    /// # Errors
+    ///
+    /// Reverts with `ReceiverRejected` if `spender` can't be called or doesn't
+    /// return the `on_approval_received` selector.
+    fn approve_and_call(&mut self, spender: H160, value: U256, data: Vec<u8>) -> Result<(), PSP22Error>;
+}
+
+/// Hooks a contract implements to accept tokens via `PSP22Callable`.
+///
+/// The messages must be exposed under their plain names, i.e. with the
+/// selectors `selector_bytes!("on_transfer_received")` and
+/// `selector_bytes!("on_approval_received")`.
+#[allow(dead_code)]
+pub trait PSP22Receiver {
+    /// Called after `value` tokens were transferred from `from` to this contract
+    /// by `operator`.
+    ///
+    /// Must return the selector of `on_transfer_received` to accept the tokens.
+    fn on_transfer_received(&mut self, operator: H160, from: H160, value: U256, data: Vec<u8>) -> [u8; 4];
+
+    /// Called after `owner` approved this contract to spend `value` tokens.
+    ///
+    /// Must return the selector of `on_approval_received` to accept the allowance.
+    fn on_approval_received(&mut self, owner: H160, value: U256, data: Vec<u8>) -> [u8; 4];
+}